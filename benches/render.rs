@@ -0,0 +1,109 @@
+//! Measures drawing a `progress::Bar`: the time, heap allocations and `write()` calls per frame.
+//!
+//! Run with `cargo bench --bench render`.
+
+use termprogress::{
+    prelude::*,
+    lines::DrawMode,
+    size::FixedSize,
+};
+use std::{
+    alloc::{
+	GlobalAlloc,
+	Layout,
+	System,
+    },
+    hint::black_box,
+    io,
+    sync::atomic::{
+	AtomicUsize,
+	Ordering,
+    },
+    time::Instant,
+};
+
+/// Counts the allocations made through it.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting
+{
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8
+    {
+	ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+	unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout)
+    {
+	unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8
+    {
+	ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+	unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+static WRITES: AtomicUsize = AtomicUsize::new(0);
+
+/// An output that discards everything, counting the calls to `write()`.
+#[derive(Debug, Default)]
+struct Writes;
+
+impl io::Write for Writes
+{
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize>
+    {
+	WRITES.fetch_add(1, Ordering::Relaxed);
+	Ok(black_box(buf).len())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()>
+    {
+	Ok(())
+    }
+}
+
+const FRAMES: u32 = 200_000;
+
+/// Draw `FRAMES` frames with `draw`, and print the cost of each.
+fn bench(name: &str, bar: &mut Bar<Writes>, mut draw: impl FnMut(&mut Bar<Writes>, u32))
+{
+    // Let the buffers grow to fit first.
+    for i in 0..100 {
+	draw(bar, i);
+    }
+    let writes = WRITES.load(Ordering::Relaxed);
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for i in 0..FRAMES {
+	draw(bar, i);
+    }
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    let writes = WRITES.load(Ordering::Relaxed) - writes;
+    println!("{name:<24} {:>8.1} ns/frame {:>6.2} allocs/frame {:>6.2} writes/frame",
+	     elapsed.as_nanos() as f64 / f64::from(FRAMES),
+	     allocations as f64 / f64::from(FRAMES),
+	     writes as f64 / f64::from(FRAMES));
+}
+
+fn main()
+{
+    let new = || Bar::<Writes>::with_size(Writes, 50, FixedSize(120))
+	.with_draw_mode(DrawMode::Terminal)
+	.with_title("hashing files");
+
+    bench("set_progress", &mut new(), |bar, i| bar.set_progress(f64::from(i % 1000) / 1000.0));
+    bench("set_position (stats)", &mut new().with_length(u64::from(FRAMES)).with_rate().with_eta(), |bar, i| bar.set_position(u64::from(i)));
+    bench("refresh", &mut new(), |bar, _| bar.refresh());
+    bench("blank", &mut new(), |bar, _| bar.blank());
+}
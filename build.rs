@@ -8,6 +8,13 @@ fn main() {
 
     println!("cargo::rustc-check-cfg=cfg(nightly)");
     println!("cargo::rustc-check-cfg=cfg(stable)");
+    println!("cargo::rustc-check-cfg=cfg(reactive)");
+
+    // `reactive` is only implemented on linux
+    if std::env::var_os("CARGO_FEATURE_REACTIVE").is_some()
+        && std::env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("linux") {
+        println!("cargo:rustc-cfg=reactive");
+    }
 
     // Set cfg flags depending on release channel
     match version_meta().unwrap().channel {
@@ -0,0 +1,330 @@
+//! Restoring the terminal if the program panics while indicators are drawn on it, and hiding the cursor while they are.
+//!
+//! A panic that unwinds past a bar or spinner leaves its line half drawn, and the panic message is printed on top of it.
+//! `install_panic_hook()` installs a hook that first blanks the line of every indicator currently drawn to a terminal, and makes the cursor visible again.
+//!
+//! Indicators created with `with_hide_cursor(true)` hide the cursor from when they are first drawn until they are finished or dropped.
+//! Indicators drawing to the same terminal share its cursor, so it is only shown again once the last of them is done.
+//!
+//! ```rust
+//! termprogress::cleanup::install_panic_hook();
+//! ```
+
+use std::{
+    fs,
+    io::{
+	self,
+	IsTerminal,
+	Write,
+    },
+    os::{
+	fd::{AsFd, BorrowedFd},
+	unix::fs::MetadataExt,
+    },
+    panic,
+    sync::{
+	Arc,
+	Mutex,
+	Once,
+	PoisonError,
+	atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+};
+
+/// Blanks the current line, and shows the cursor.
+const RESTORE: &[u8] = b"\r\x1B[2K\x1B[?25h";
+
+pub(crate) const HIDE_CURSOR: &str = "\x1B[?25l";
+pub(crate) const SHOW_CURSOR: &str = "\x1B[?25h";
+
+/// The indicators drawing to a terminal, see `Registration`.
+static ACTIVE: Mutex<Vec<Entry>> = Mutex::new(Vec::new());
+
+/// The number of indicators hiding the cursor of each terminal, see `Cursor`.
+static HIDING: Mutex<Vec<(StreamId, usize)>> = Mutex::new(Vec::new());
+
+/// Identifies the file (e.g. terminal) a file descriptor refers to, so indicators drawing to e.g. both `stdout` and `stderr` of one terminal know they share its cursor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct StreamId
+{
+    dev: u64,
+    ino: u64,
+}
+
+impl StreamId
+{
+    /// The file `output` refers to, if it can be found out.
+    pub(crate) fn of(output: &impl AsFd) -> Option<Self>
+    {
+	let file = fs::File::from(output.as_fd().try_clone_to_owned().ok()?);
+	let meta = file.metadata().ok()?;
+	Some(Self { dev: meta.dev(), ino: meta.ino() })
+    }
+}
+
+#[derive(Debug, Default)]
+struct State
+{
+    /// Has the indicator drawn anything since it was registered?
+    active: AtomicBool,
+    /// Has the panic hook blanked the indicator's line?
+    cleaned: AtomicBool,
+}
+
+#[derive(Debug)]
+struct Entry
+{
+    id: u64,
+    /// A duplicate of the indicator's file descriptor, so the hook never has to wait for the indicator itself.
+    out: fs::File,
+    state: Arc<State>,
+}
+
+/// An indicator's place in the panic hook's list of indicators to restore. It is removed when this is dropped.
+#[derive(Debug)]
+pub(crate) struct Registration
+{
+    id: u64,
+    state: Arc<State>,
+}
+
+impl Registration
+{
+    /// Register an indicator drawing to `output`, if it is a terminal.
+    pub(crate) fn new(output: &impl AsFd) -> Option<Self>
+    {
+	let fd = output.as_fd();
+	if !fd.is_terminal() {
+	    return None;
+	}
+	Self::register(fd).ok()
+    }
+
+    fn register(fd: BorrowedFd<'_>) -> io::Result<Self>
+    {
+	static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+	let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+	let state = Arc::new(State::default());
+	let out = fs::File::from(fd.try_clone_to_owned()?);
+	ACTIVE.lock().unwrap_or_else(PoisonError::into_inner).push(Entry { id, out, state: Arc::clone(&state) });
+	Ok(Self { id, state })
+    }
+
+    /// The indicator has drawn on its line, so it needs blanking if the program panics.
+    #[inline]
+    pub(crate) fn activate(&self)
+    {
+	self.state.active.store(true, Ordering::Relaxed);
+    }
+
+    /// Has the panic hook already blanked the indicator's line? If so, it should not draw it again.
+    #[inline]
+    pub(crate) fn is_cleaned(&self) -> bool
+    {
+	self.state.cleaned.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Registration
+{
+    fn drop(&mut self)
+    {
+	ACTIVE.lock().unwrap_or_else(PoisonError::into_inner).retain(|entry| entry.id != self.id);
+    }
+}
+
+/// Whether an indicator hides the cursor, and whether it currently is.
+///
+/// Hiding is counted per terminal: `hide()` only asks for the escape to be written by the first indicator to hide a terminal's cursor, and `show()` by the last to stop.
+/// Indicators drawing to a file descriptor are counted with the others drawing to the same file, and the rest (e.g. ones drawing to a `Vec<u8>`) are counted alone.
+/// If the indicator is dropped while hiding the cursor, it stops being counted.
+#[derive(Debug, Default)]
+pub(crate) struct Cursor
+{
+    hide: bool,
+    hidden: AtomicBool,
+    stream: Option<StreamId>,
+}
+
+impl Cursor
+{
+    /// Should the cursor be hidden while the indicator is drawn?
+    #[inline]
+    pub(crate) fn hides(&self) -> bool
+    {
+	self.hide
+    }
+
+    #[inline]
+    pub(crate) fn set_hide(&mut self, hide: bool)
+    {
+	self.hide = hide;
+    }
+
+    /// Share the cursor of `stream` with the other indicators drawing to it.
+    #[inline]
+    pub(crate) fn set_stream(&mut self, stream: Option<StreamId>)
+    {
+	self.stream = stream;
+    }
+
+    /// Start hiding the cursor, if enabled and not already.
+    ///
+    /// Returns whether `HIDE_CURSOR` should be written, i.e. no other indicator is hiding it yet.
+    pub(crate) fn hide(&self) -> bool
+    {
+	if !self.hide || self.hidden.swap(true, Ordering::Relaxed) {
+	    return false;
+	}
+	let Some(stream) = self.stream else { return true };
+	let mut hiding = HIDING.lock().unwrap_or_else(PoisonError::into_inner);
+	match hiding.iter_mut().find(|(id, _)| *id == stream) {
+	    Some((_, count)) => {
+		*count += 1;
+		false
+	    },
+	    None => {
+		hiding.push((stream, 1));
+		true
+	    },
+	}
+    }
+
+    /// Stop hiding the cursor, if it is.
+    ///
+    /// Returns whether `SHOW_CURSOR` should be written, i.e. no other indicator is still hiding it.
+    pub(crate) fn show(&self) -> bool
+    {
+	if !self.hidden.swap(false, Ordering::Relaxed) {
+	    return false;
+	}
+	let Some(stream) = self.stream else { return true };
+	let mut hiding = HIDING.lock().unwrap_or_else(PoisonError::into_inner);
+	let Some(idx) = hiding.iter().position(|(id, _)| *id == stream) else { return true };
+	hiding[idx].1 -= 1;
+	if hiding[idx].1 == 0 {
+	    hiding.swap_remove(idx);
+	    true
+	} else {
+	    false
+	}
+    }
+}
+
+impl Drop for Cursor
+{
+    #[inline]
+    fn drop(&mut self)
+    {
+	self.show();
+    }
+}
+
+/// Blank the line of every registered indicator picked by `pick` that has drawn, and show the cursor.
+fn restore(pick: impl Fn(u64) -> bool)
+{
+    let active = ACTIVE.lock().unwrap_or_else(PoisonError::into_inner);
+    for entry in active.iter().filter(|entry| pick(entry.id) && entry.state.active.load(Ordering::Relaxed)) {
+	entry.state.cleaned.store(true, Ordering::Relaxed);
+	let _ = (&entry.out).write_all(RESTORE);
+    }
+}
+
+/// Install a panic hook that restores the terminal before the panic message is printed.
+///
+/// The line of every `progress::Bar` and `spinner::Spin` drawing to a terminal is blanked, and the cursor is made visible again.
+/// Indicators blanked this way are not finished again when they are dropped as the panic unwinds.
+/// The previous hook (by default, the one printing the message) is called afterwards. Installing more than once does nothing.
+pub fn install_panic_hook()
+{
+    static INSTALLED: Once = Once::new();
+    INSTALLED.call_once(|| {
+	let previous = panic::take_hook();
+	panic::set_hook(Box::new(move |info| {
+	    restore(|_| true);
+	    previous(info);
+	}));
+    });
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn restoring()
+    {
+	let path = std::env::temp_dir().join(format!("termprogress-cleanup-{}", std::process::id()));
+	let file = fs::File::create(&path).unwrap();
+	let idle = Registration::register(file.as_fd()).unwrap();
+	let drawn = Registration::register(file.as_fd()).unwrap();
+	drawn.activate();
+	let finished = Registration::register(file.as_fd()).unwrap();
+	finished.activate();
+	let finished_id = finished.id;
+	drop(finished);
+
+	// Other tests may have registered indicators of their own.
+	let ours = [idle.id, drawn.id, finished_id];
+	restore(|id| ours.contains(&id));
+	assert!(drawn.is_cleaned());
+	assert!(!idle.is_cleaned(), "an indicator that never drew should be left alone");
+
+	let mut written = Vec::new();
+	fs::File::open(&path).unwrap().read_to_end(&mut written).unwrap();
+	let _ = fs::remove_file(&path);
+	assert_eq!(written, RESTORE);
+    }
+
+    #[test]
+    fn hiding()
+    {
+	let stream = Some(StreamId { dev: u64::MAX, ino: u64::MAX });
+	let cursor = || {
+	    let mut cursor = Cursor::default();
+	    cursor.set_hide(true);
+	    cursor.set_stream(stream);
+	    cursor
+	};
+	let (first, second) = (cursor(), cursor());
+	assert!(first.hide());
+	assert!(!first.hide(), "already hidden");
+	assert!(!second.hide(), "the first is hiding it already");
+	assert!(!first.show(), "the second is still hiding it");
+	assert!(!first.show(), "already shown");
+
+	let third = cursor();
+	assert!(!third.hide());
+	drop(second);
+	assert!(third.show());
+
+	let mut alone = cursor();
+	alone.set_stream(None);
+	assert!(alone.hide() && alone.show());
+	assert!(!Cursor::default().hide(), "hiding is off by default");
+    }
+
+    #[test]
+    fn hiding_on_one_stream()
+    {
+	use crate::{prelude::*, lines::DrawMode};
+	let (mut reader, writer) = io::pipe().unwrap();
+	let mut bar: Bar<io::PipeWriter> = Bar::with_max(writer.try_clone().unwrap(), 10, 40)
+	    .with_draw_mode(DrawMode::Terminal)
+	    .with_hide_cursor(true);
+	let mut spin = Spin::with_fd(writer, "", Default::default()).with_hide_cursor(true);
+	bar.set_progress(0.5);
+	spin.bump();
+	bar.complete().unwrap();
+	spin.complete().unwrap();
+
+	let mut written = Vec::new();
+	reader.read_to_end(&mut written).unwrap();
+	let written = String::from_utf8(written).unwrap();
+	assert_eq!(written.matches(HIDE_CURSOR).count(), 1, "{written:?}");
+	assert_eq!(written.matches(SHOW_CURSOR).count(), 1, "{written:?}");
+	assert!(written.ends_with(SHOW_CURSOR), "only the last indicator to finish should show the cursor: {written:?}");
+    }
+}
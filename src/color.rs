@@ -0,0 +1,403 @@
+//! ANSI colours and text styles.
+//!
+//! Styles are rendered at a `ColorLevel`, which is detected from the environment and the output device: colours the terminal does not support are downgraded to the closest ones it does.
+//!
+//! # Detection
+//! * If `NO_COLOR` is set (and not empty), nothing is coloured.
+//! * If `CLICOLOR_FORCE` is set (and not `0`), colours are used even if the output is not a terminal.
+//! * Otherwise, if the output is not a terminal or `CLICOLOR` is `0`, nothing is coloured.
+//! * The level is then decided by `COLORTERM` (`truecolor` or `24bit`) and `TERM` (e.g. `xterm-256color`, or `dumb`.)
+
+use std::{
+    env,
+    ffi::OsString,
+    fmt::Write,
+};
+
+/// A colour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Color
+{
+    /// One of the 16 basic colours (`0..16`), see the associated constants.
+    Ansi(u8),
+    /// One of the 256 indexed colours.
+    Fixed(u8),
+    /// A 24-bit colour.
+    Rgb(u8, u8, u8),
+}
+
+impl Color
+{
+    pub const BLACK: Self = Self::Ansi(0);
+    pub const RED: Self = Self::Ansi(1);
+    pub const GREEN: Self = Self::Ansi(2);
+    pub const YELLOW: Self = Self::Ansi(3);
+    pub const BLUE: Self = Self::Ansi(4);
+    pub const MAGENTA: Self = Self::Ansi(5);
+    pub const CYAN: Self = Self::Ansi(6);
+    pub const WHITE: Self = Self::Ansi(7);
+    pub const BRIGHT_BLACK: Self = Self::Ansi(8);
+    pub const BRIGHT_RED: Self = Self::Ansi(9);
+    pub const BRIGHT_GREEN: Self = Self::Ansi(10);
+    pub const BRIGHT_YELLOW: Self = Self::Ansi(11);
+    pub const BRIGHT_BLUE: Self = Self::Ansi(12);
+    pub const BRIGHT_MAGENTA: Self = Self::Ansi(13);
+    pub const BRIGHT_CYAN: Self = Self::Ansi(14);
+    pub const BRIGHT_WHITE: Self = Self::Ansi(15);
+
+    /// The closest colour to this one that can be rendered at `level`.
+    ///
+    /// `None` if `level` is `ColorLevel::None`.
+    pub fn downgrade(self, level: ColorLevel) -> Option<Self>
+    {
+	Some(match (self, level) {
+	    (_, ColorLevel::None) => return None,
+	    (Self::Rgb(..), ColorLevel::TrueColor) | (Self::Fixed(_), ColorLevel::TrueColor | ColorLevel::Ansi256) => self,
+	    (Self::Ansi(n), _) => Self::Ansi(n & 0xf),
+	    (Self::Rgb(r, g, b), ColorLevel::Ansi256) => Self::Fixed(rgb_to_fixed(r, g, b)),
+	    (Self::Fixed(n), ColorLevel::Ansi16) if n < 16 => Self::Ansi(n),
+	    (Self::Fixed(n), ColorLevel::Ansi16) => {
+		let (r, g, b) = fixed_to_rgb(n);
+		Self::Ansi(rgb_to_ansi(r, g, b))
+	    },
+	    (Self::Rgb(r, g, b), ColorLevel::Ansi16) => Self::Ansi(rgb_to_ansi(r, g, b)),
+	})
+    }
+
+    /// Write the SGR parameters for this colour as the foreground (or background, if `background` is set.)
+    fn write_sgr(self, out: &mut String, background: bool)
+    {
+	let base = if background { 40 } else { 30 };
+	// Writing to a `String` cannot fail.
+	let _ = match self {
+	    Self::Ansi(n) if n < 8 => write!(out, "{}", base + n),
+	    Self::Ansi(n) => write!(out, "{}", base + 60 + (n & 0x7)),
+	    Self::Fixed(n) => write!(out, "{};5;{}", base + 8, n),
+	    Self::Rgb(r, g, b) => write!(out, "{};2;{};{};{}", base + 8, r, g, b),
+	};
+    }
+}
+
+/// The channel levels of the 6x6x6 colour cube in the 256 indexed colours.
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// The (xterm default) values of the 16 basic colours.
+const BASIC: [(u8, u8, u8); 16] = [
+    (0, 0, 0), (205, 0, 0), (0, 205, 0), (205, 205, 0), (0, 0, 238), (205, 0, 205), (0, 205, 205), (229, 229, 229),
+    (127, 127, 127), (255, 0, 0), (0, 255, 0), (255, 255, 0), (92, 92, 255), (255, 0, 255), (0, 255, 255), (255, 255, 255),
+];
+
+fn fixed_to_rgb(n: u8) -> (u8, u8, u8)
+{
+    match n {
+	0..16 => BASIC[n as usize],
+	16..232 => {
+	    let n = n - 16;
+	    (CUBE[(n / 36) as usize], CUBE[((n / 6) % 6) as usize], CUBE[(n % 6) as usize])
+	},
+	_ => {
+	    let grey = 8 + 10 * (n - 232);
+	    (grey, grey, grey)
+	},
+    }
+}
+
+fn rgb_to_fixed(r: u8, g: u8, b: u8) -> u8
+{
+    let cube = |v: u8| CUBE.iter().enumerate().min_by_key(|&(_, &c)| c.abs_diff(v)).map(|(i, _)| i as u8).unwrap_or(0);
+    let (cr, cg, cb) = (cube(r), cube(g), cube(b));
+    let in_cube = 16 + 36 * cr + 6 * cg + cb;
+    // Greys are often closer to one of the 24 grey ramp colours than to the cube.
+    let avg = ((r as u16 + g as u16 + b as u16) / 3) as u8;
+    let grey = 232 + (avg.saturating_sub(3) / 10).min(23);
+    if distance(fixed_to_rgb(grey), (r, g, b)) < distance(fixed_to_rgb(in_cube), (r, g, b)) {
+	grey
+    } else {
+	in_cube
+    }
+}
+
+fn rgb_to_ansi(r: u8, g: u8, b: u8) -> u8
+{
+    (0..16u8).min_by_key(|&n| distance(BASIC[n as usize], (r, g, b))).unwrap_or(0)
+}
+
+/// The squared distance between two colours.
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32
+{
+    let d = |a: u8, b: u8| (a.abs_diff(b) as u32).pow(2);
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+/// How many colours an output supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum ColorLevel
+{
+    /// No colours or styles at all.
+    #[default]
+    None,
+    /// The 16 basic colours.
+    Ansi16,
+    /// The 256 indexed colours.
+    Ansi256,
+    /// 24-bit colours.
+    TrueColor,
+}
+
+impl ColorLevel
+{
+    /// The level the terminal supports according to `TERM` and `COLORTERM`, regardless of what the output is or whether colour is disabled.
+    #[inline]
+    pub fn from_term() -> Self
+    {
+	Self::from_term_with(|name| env::var_os(name))
+    }
+
+    /// `from_term()`, with environment variables looked up by `var`.
+    fn from_term_with(var: impl Fn(&str) -> Option<OsString>) -> Self
+    {
+	let string = |name| var(name).and_then(|v| v.into_string().ok()).unwrap_or_default();
+	let (colorterm, term) = (string("COLORTERM"), string("TERM"));
+	if colorterm == "truecolor" || colorterm == "24bit" || term.ends_with("-direct") {
+	    Self::TrueColor
+	} else if term.contains("256color") {
+	    Self::Ansi256
+	} else if term.is_empty() || term == "dumb" {
+	    Self::None
+	} else {
+	    Self::Ansi16
+	}
+    }
+
+    /// The level to use for an output, which `is_terminal` or not.
+    ///
+    /// See the module documentation for how it is detected.
+    #[inline]
+    pub fn detect(is_terminal: bool) -> Self
+    {
+	Self::detect_with(is_terminal, |name| env::var_os(name))
+    }
+
+    /// `detect()`, with environment variables looked up by `var`.
+    fn detect_with(is_terminal: bool, var: impl Fn(&str) -> Option<OsString>) -> Self
+    {
+	if var("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+	    return Self::None;
+	}
+	if var("CLICOLOR_FORCE").is_some_and(|v| !v.is_empty() && v != "0") {
+	    return Self::from_term_with(var).max(Self::Ansi16);
+	}
+	if var("CLICOLOR").is_some_and(|v| v == "0") || !is_terminal {
+	    return Self::None;
+	}
+	Self::from_term_with(var)
+    }
+}
+
+/// A text style: foreground and background colours, and attributes.
+///
+/// The default style is plain, and renders no escape codes at all.
+/// ```rust
+/// # use termprogress::color::{Style, Color};
+/// let style = Style::new().fg(Color::GREEN).bold();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Style
+{
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+impl Style
+{
+    /// A plain style.
+    #[inline]
+    pub const fn new() -> Self
+    {
+	Self {
+	    fg: None,
+	    bg: None,
+	    bold: false,
+	    dim: false,
+	    italic: false,
+	    underline: false,
+	}
+    }
+
+    /// With the foreground colour `color`.
+    #[inline]
+    pub const fn fg(mut self, color: Color) -> Self
+    {
+	self.fg = Some(color);
+	self
+    }
+
+    /// With the background colour `color`.
+    #[inline]
+    pub const fn bg(mut self, color: Color) -> Self
+    {
+	self.bg = Some(color);
+	self
+    }
+
+    /// Bold.
+    #[inline]
+    pub const fn bold(mut self) -> Self
+    {
+	self.bold = true;
+	self
+    }
+
+    /// Dimmed.
+    #[inline]
+    pub const fn dim(mut self) -> Self
+    {
+	self.dim = true;
+	self
+    }
+
+    /// Italic.
+    #[inline]
+    pub const fn italic(mut self) -> Self
+    {
+	self.italic = true;
+	self
+    }
+
+    /// Underlined.
+    #[inline]
+    pub const fn underline(mut self) -> Self
+    {
+	self.underline = true;
+	self
+    }
+
+    /// Does this style render no escape codes?
+    #[inline]
+    pub fn is_plain(&self) -> bool
+    {
+	*self == Self::new()
+    }
+
+    /// Write `text` into `out` in this style, as rendered at `level`.
+    ///
+    /// The style is reset after `text`. If nothing would be styled at `level`, or `text` is empty, it is written as-is.
+    pub(crate) fn paint(&self, level: ColorLevel, text: &str, out: &mut String)
+    {
+	if level == ColorLevel::None || self.is_plain() || text.is_empty() {
+	    out.push_str(text);
+	    return;
+	}
+	let start = out.len();
+	out.push_str("\x1B[");
+	let sep = |out: &mut String| if out.len() > start + 2 { out.push(';') };
+	for (set, code) in [(self.bold, "1"), (self.dim, "2"), (self.italic, "3"), (self.underline, "4")] {
+	    if set {
+		sep(out);
+		out.push_str(code);
+	    }
+	}
+	if let Some(fg) = self.fg.and_then(|c| c.downgrade(level)) {
+	    sep(out);
+	    fg.write_sgr(out, false);
+	}
+	if let Some(bg) = self.bg.and_then(|c| c.downgrade(level)) {
+	    sep(out);
+	    bg.write_sgr(out, true);
+	}
+	out.push('m');
+	out.push_str(text);
+	out.push_str("\x1B[0m");
+    }
+}
+
+/// The styles of the parts of a `progress::Bar`'s line.
+///
+/// All parts are plain by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct BarColors
+{
+    /// The filled part of the bar.
+    pub filled: Style,
+    /// The empty part of the bar.
+    pub empty: Style,
+    /// The percentage.
+    pub percent: Style,
+    /// The title.
+    pub title: Style,
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use crate::width;
+
+    #[test]
+    fn painting()
+    {
+	let style = Style::new().fg(Color::Rgb(255, 0, 0)).bold();
+	let paint = |level| {
+	    let mut out = String::new();
+	    style.paint(level, "hi", &mut out);
+	    out
+	};
+	assert_eq!(paint(ColorLevel::TrueColor), "\x1B[1;38;2;255;0;0mhi\x1B[0m");
+	assert_eq!(paint(ColorLevel::Ansi256), "\x1B[1;38;5;196mhi\x1B[0m");
+	assert_eq!(paint(ColorLevel::Ansi16), "\x1B[1;91mhi\x1B[0m");
+	assert_eq!(paint(ColorLevel::None), "hi");
+	assert_eq!(width::width(&paint(ColorLevel::TrueColor)), 2);
+    }
+
+    #[test]
+    fn downgrading()
+    {
+	assert_eq!(Color::Rgb(128, 128, 128).downgrade(ColorLevel::Ansi256), Some(Color::Fixed(244)));
+	assert_eq!(Color::Fixed(21).downgrade(ColorLevel::Ansi16), Some(Color::BLUE));
+	assert_eq!(Color::Fixed(9).downgrade(ColorLevel::Ansi16), Some(Color::BRIGHT_RED));
+	assert_eq!(Color::GREEN.downgrade(ColorLevel::TrueColor), Some(Color::GREEN));
+	assert_eq!(Color::GREEN.downgrade(ColorLevel::None), None);
+    }
+
+    /// Detect the level of an output that `is_terminal` or not, in an environment of only `vars`.
+    fn detect(is_terminal: bool, vars: &[(&str, &str)]) -> ColorLevel
+    {
+	ColorLevel::detect_with(is_terminal, |name| vars.iter().find(|(n, _)| *n == name).map(|(_, v)| v.into()))
+    }
+
+    #[test]
+    fn detecting()
+    {
+	const XTERM: (&str, &str) = ("TERM", "xterm");
+	// `TERM` and `COLORTERM`.
+	assert_eq!(detect(true, &[XTERM]), ColorLevel::Ansi16);
+	assert_eq!(detect(true, &[("TERM", "xterm-256color")]), ColorLevel::Ansi256);
+	assert_eq!(detect(true, &[("TERM", "xterm-direct")]), ColorLevel::TrueColor);
+	assert_eq!(detect(true, &[XTERM, ("COLORTERM", "truecolor")]), ColorLevel::TrueColor);
+	assert_eq!(detect(true, &[XTERM, ("COLORTERM", "24bit")]), ColorLevel::TrueColor);
+	assert_eq!(detect(true, &[("TERM", "dumb")]), ColorLevel::None);
+	assert_eq!(detect(true, &[]), ColorLevel::None);
+
+	// `NO_COLOR` wins over everything, unless it is empty.
+	assert_eq!(detect(true, &[XTERM, ("NO_COLOR", "1")]), ColorLevel::None);
+	assert_eq!(detect(true, &[XTERM, ("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1")]), ColorLevel::None);
+	assert_eq!(detect(true, &[XTERM, ("NO_COLOR", "")]), ColorLevel::Ansi16);
+
+	// `CLICOLOR_FORCE` colours outputs that are not terminals, and at least at `Ansi16`.
+	assert_eq!(detect(false, &[XTERM]), ColorLevel::None);
+	assert_eq!(detect(false, &[XTERM, ("CLICOLOR_FORCE", "1")]), ColorLevel::Ansi16);
+	assert_eq!(detect(false, &[("TERM", "xterm-256color"), ("CLICOLOR_FORCE", "1")]), ColorLevel::Ansi256);
+	assert_eq!(detect(false, &[("TERM", "dumb"), ("CLICOLOR_FORCE", "1")]), ColorLevel::Ansi16);
+	assert_eq!(detect(false, &[XTERM, ("CLICOLOR_FORCE", "0")]), ColorLevel::None);
+	assert_eq!(detect(false, &[XTERM, ("CLICOLOR_FORCE", "")]), ColorLevel::None);
+
+	// `CLICOLOR=0` turns colours off for terminals too.
+	assert_eq!(detect(true, &[XTERM, ("CLICOLOR", "0")]), ColorLevel::None);
+	assert_eq!(detect(true, &[XTERM, ("CLICOLOR", "1")]), ColorLevel::Ansi16);
+	assert_eq!(detect(false, &[XTERM, ("CLICOLOR", "0"), ("CLICOLOR_FORCE", "1")]), ColorLevel::Ansi16);
+    }
+}
@@ -0,0 +1,113 @@
+//! Handling I/O errors that occur while rendering indicators.
+
+use super::*;
+use std::{
+    fmt,
+    io,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// What an indicator does when writing to its output fails during an infallible display operation (e.g. `Display::refresh()`.)
+///
+/// The `try_*` family of methods on `progress::Bar` and `spinner::Spin` return errors to the caller directly, and do not consult the policy.
+#[derive(Default)]
+pub enum ErrorPolicy
+{
+    /// Silently discard the error.
+    #[default]
+    Ignore,
+    /// Keep the most recent error, which can be retrieved with the indicator's `take_error()` method.
+    Remember,
+    /// Call a function with the error.
+    Callback(Box<dyn Fn(&io::Error) + Send + Sync>),
+    /// Stop rendering entirely once the output reports `io::ErrorKind::BrokenPipe`, so a closed pipe is not written to endlessly. Other errors are discarded.
+    DisableOnBrokenPipe,
+}
+
+impl ErrorPolicy
+{
+    /// Create a policy that calls `fun` with every error.
+    #[inline]
+    pub fn callback<F>(fun: F) -> Self
+    where F: Fn(&io::Error) + Send + Sync + 'static
+    {
+	Self::Callback(Box::new(fun))
+    }
+}
+
+impl fmt::Debug for ErrorPolicy
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+	match self {
+	    Self::Ignore => f.write_str("Ignore"),
+	    Self::Remember => f.write_str("Remember"),
+	    Self::Callback(_) => f.write_str("Callback(..)"),
+	    Self::DisableOnBrokenPipe => f.write_str("DisableOnBrokenPipe"),
+	}
+    }
+}
+
+/// The per-indicator state of an `ErrorPolicy`.
+#[derive(Debug, Default)]
+pub(crate) struct ErrorState
+{
+    policy: ErrorPolicy,
+    last: AtomicRefCell<Option<io::Error>>,
+    disabled: AtomicBool,
+}
+
+impl ErrorState
+{
+    #[inline]
+    pub fn new(policy: ErrorPolicy) -> Self
+    {
+	Self {
+	    policy,
+	    ..Default::default()
+	}
+    }
+
+    #[inline]
+    pub fn set_policy(&mut self, policy: ErrorPolicy)
+    {
+	self.policy = policy;
+    }
+
+    /// Has rendering been disabled by the policy?
+    #[inline]
+    pub fn is_disabled(&self) -> bool
+    {
+	self.disabled.load(Ordering::Relaxed)
+    }
+
+    /// Take the last remembered error, if there is one.
+    ///
+    /// If another thread is currently handling an error, `None` is returned.
+    #[inline]
+    pub fn take(&self) -> Option<io::Error>
+    {
+	self.last.try_borrow_mut().ok()?.take()
+    }
+
+    /// Apply the policy to the result of a display operation.
+    pub fn handle(&self, result: io::Result<()>)
+    {
+	let Err(error) = result else { return };
+	match &self.policy {
+	    ErrorPolicy::Ignore => (),
+	    ErrorPolicy::Remember => {
+		// If another thread is already storing one, just drop ours.
+		if let Ok(mut last) = self.last.try_borrow_mut() {
+		    *last = Some(error);
+		}
+	    },
+	    ErrorPolicy::Callback(fun) => fun(&error),
+	    ErrorPolicy::DisableOnBrokenPipe => {
+		if error.kind() == io::ErrorKind::BrokenPipe {
+		    self.disabled.store(true, Ordering::Relaxed);
+		}
+	    },
+	}
+    }
+}
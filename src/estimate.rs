@@ -0,0 +1,168 @@
+//! Estimating elapsed and remaining time from the history of progress updates.
+
+use std::time::{
+    Duration,
+    Instant,
+};
+
+/// The default half-life of `Estimator`'s smoothing.
+pub const DEFAULT_HALF_LIFE: Duration = Duration::from_secs(5);
+
+/// Estimates the rate of progress, and so the time remaining, from a series of progress updates.
+///
+/// The rate is an exponentially weighted moving average (EWMA) of the rate between each update, weighted by the time between them.
+/// A sample's weight halves every `half_life` after it was recorded, so the estimate follows changes in speed while bursts of many updates in quick succession (or long gaps without any) are smoothed out.
+#[derive(Debug, Clone)]
+pub struct Estimator
+{
+    started: Instant,
+    /// The time and progress of the last sample.
+    last: (Instant, f64),
+    /// Smoothed progress per second.
+    rate: Option<f64>,
+    half_life: Duration,
+}
+
+impl Estimator
+{
+    /// Create a new estimator starting at progress `0.0` at `now`.
+    #[inline]
+    pub fn new(now: Instant) -> Self
+    {
+	Self::with_half_life(now, DEFAULT_HALF_LIFE)
+    }
+
+    /// Create a new estimator starting at progress `0.0` at `now`, where the weight of a sample halves every `half_life`.
+    #[inline]
+    pub fn with_half_life(now: Instant, half_life: Duration) -> Self
+    {
+	Self {
+	    started: now,
+	    last: (now, 0.0),
+	    rate: None,
+	    half_life,
+	}
+    }
+
+    /// Start estimating the rate over from progress `0.0` at `now`.
+    ///
+    /// The time elapsed is still counted from when the estimator was created.
+    #[inline]
+    pub fn reset(&mut self, now: Instant)
+    {
+	*self = Self {
+	    started: self.started,
+	    ..Self::with_half_life(now, self.half_life)
+	};
+    }
+
+    /// Record the `progress` (in `0..=1`) at `now`.
+    ///
+    /// Any other increasing quantity (e.g. a bar's position) can be recorded instead to estimate its `rate()`, though `eta()` is then meaningless.
+    /// If the progress went backwards, the estimate of the rate starts over (see `reset()`.)
+    pub fn record(&mut self, progress: f64, now: Instant)
+    {
+	let (then, last) = self.last;
+	if progress < last {
+	    self.reset(now);
+	    self.last = (now, progress);
+	    return;
+	}
+	let dt = now.saturating_duration_since(then);
+	if dt.is_zero() {
+	    // Fold it into the next sample.
+	    return;
+	}
+	let sample = (progress - last) / dt.as_secs_f64();
+	let weight = 1.0 - 0.5f64.powf(dt.as_secs_f64() / self.half_life.as_secs_f64());
+	self.rate = Some(match self.rate {
+	    Some(rate) => rate + (sample - rate) * weight,
+	    None => sample,
+	});
+	self.last = (now, progress);
+    }
+
+    /// The time elapsed since the estimator was created.
+    #[inline]
+    pub fn elapsed(&self, now: Instant) -> Duration
+    {
+	now.saturating_duration_since(self.started)
+    }
+
+    /// The smoothed rate of progress, in progress per second.
+    ///
+    /// `None` until at least one sample has been recorded.
+    #[inline]
+    pub fn rate(&self) -> Option<f64>
+    {
+	self.rate
+    }
+
+    /// The estimated time remaining until progress reaches `1.0`, from the last recorded progress.
+    ///
+    /// `None` if there is no estimate yet, or progress has stalled.
+    pub fn eta(&self) -> Option<Duration>
+    {
+	let rate = self.rate?;
+	let remaining = (1.0 - self.last.1).max(0.0);
+	if remaining == 0.0 {
+	    return Some(Duration::ZERO);
+	}
+	if rate.is_nan() || rate <= 0.0 {
+	    return None;
+	}
+	Duration::try_from_secs_f64(remaining / rate).ok()
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    #[test]
+    fn steady_progress()
+    {
+	let start = Instant::now();
+	let mut est = Estimator::new(start);
+	assert_eq!(est.eta(), None);
+	for i in 1..=5 {
+	    est.record(i as f64 * 0.1, start + Duration::from_secs(i));
+	}
+	assert_eq!(est.elapsed(start + Duration::from_secs(5)), Duration::from_secs(5));
+	let eta = est.eta().unwrap().as_secs_f64();
+	assert!((eta - 5.0).abs() < 0.001, "eta was {eta}");
+    }
+
+    #[test]
+    fn bursty_progress()
+    {
+	let start = Instant::now();
+	let mut est = Estimator::new(start);
+	// 10% in a burst every 10 seconds, i.e. 1% per second on average.
+	for burst in 1..=4u64 {
+	    let at = start + Duration::from_secs(burst * 10);
+	    for i in 1..=10u64 {
+		est.record((burst - 1) as f64 * 0.1 + i as f64 * 0.01, at + Duration::from_millis(i));
+	    }
+	}
+	let eta = est.eta().unwrap().as_secs_f64();
+	// The remaining 60% at 1% per second, give or take the burst at the end.
+	assert!((30.0..90.0).contains(&eta), "eta was {eta}");
+    }
+
+    #[test]
+    fn stalled_and_reset()
+    {
+	let start = Instant::now();
+	let mut est = Estimator::new(start);
+	est.record(0.0, start + Duration::from_secs(1));
+	assert_eq!(est.eta(), None);
+	est.record(0.5, start + Duration::from_secs(2));
+	assert!(est.eta().is_some());
+	est.record(0.1, start + Duration::from_secs(3));
+	assert_eq!(est.eta(), None);
+	assert_eq!(est.rate(), None);
+	assert_eq!(est.elapsed(start + Duration::from_secs(3)), Duration::from_secs(3), "going backwards should not reset the time elapsed");
+    }
+}
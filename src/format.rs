@@ -0,0 +1,219 @@
+//! Human-readable formatting of counts, sizes, durations and rates, shared by all indicators.
+
+use std::{
+    fmt,
+    time::Duration,
+};
+
+/// The prefixes used to scale byte sizes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ByteUnits
+{
+    /// Powers of 1024: `KiB`, `MiB`, `GiB`, ...
+    #[default]
+    Iec,
+    /// Powers of 1000: `kB`, `MB`, `GB`, ...
+    Si,
+}
+
+impl ByteUnits
+{
+    #[inline]
+    const fn base(self) -> f64
+    {
+	match self {
+	    Self::Iec => 1024.0,
+	    Self::Si => 1000.0,
+	}
+    }
+
+    #[inline]
+    const fn prefixes(self) -> &'static [&'static str]
+    {
+	match self {
+	    Self::Iec => &["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"],
+	    Self::Si => &["B", "kB", "MB", "GB", "TB", "PB", "EB"],
+	}
+    }
+}
+
+/// What an indicator's position, length and rate count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CountUnit
+{
+    /// Discrete items, formatted like `1,234` and `450 it/s`.
+    #[default]
+    Items,
+    /// Bytes, formatted like `1.2 MiB` and `12.3 MiB/s`.
+    Bytes(ByteUnits),
+}
+
+impl CountUnit
+{
+    /// Format a count in this unit.
+    #[inline]
+    pub fn count(self, value: u64) -> HumanUnit
+    {
+	HumanUnit(value, self)
+    }
+
+    /// Format a rate per second in this unit.
+    #[inline]
+    pub fn rate(self, per_sec: f64) -> HumanRate
+    {
+	HumanRate(per_sec, self)
+    }
+}
+
+/// Formats an integer count with `,` separating every 3 digits, e.g. `1,234,567`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HumanCount(pub u64);
+
+impl fmt::Display for HumanCount
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+	// `u64::MAX` has 20 digits, written from the end without allocating.
+	let mut digits = [0u8; 20];
+	let mut start = digits.len();
+	let mut n = self.0;
+	loop {
+	    start -= 1;
+	    digits[start] = b'0' + (n % 10) as u8;
+	    n /= 10;
+	    if n == 0 {
+		break;
+	    }
+	}
+	let digits = &digits[start..];
+	for (i, &digit) in digits.iter().enumerate() {
+	    if i > 0 && (digits.len() - i).is_multiple_of(3) {
+		f.write_str(",")?;
+	    }
+	    fmt::Write::write_char(f, char::from(digit))?;
+	}
+	Ok(())
+    }
+}
+
+/// Formats a size in bytes scaled to the largest fitting prefix, e.g. `512 B` or `12.3 MiB`.
+///
+/// The precision defaults to 1 decimal place, and can be set with the format string (e.g. `{:.2}`.)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HumanBytes(pub f64, pub ByteUnits);
+
+impl fmt::Display for HumanBytes
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+	let Self(mut value, units) = *self;
+	let prefixes = units.prefixes();
+	let mut prefix = 0;
+	while value >= units.base() && prefix < prefixes.len() - 1 {
+	    value /= units.base();
+	    prefix += 1;
+	}
+	if prefix == 0 {
+	    write!(f, "{:.0} {}", value, prefixes[0])
+	} else {
+	    write!(f, "{:.*} {}", f.precision().unwrap_or(1), value, prefixes[prefix])
+	}
+    }
+}
+
+/// Formats a duration as a clock, e.g. `01:05` or `1:01:05`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HumanDuration(pub Duration);
+
+impl fmt::Display for HumanDuration
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+	let secs = self.0.as_secs();
+	let (hours, mins, secs) = (secs / 3600, (secs / 60) % 60, secs % 60);
+	if hours > 0 {
+	    write!(f, "{}:{:02}:{:02}", hours, mins, secs)
+	} else {
+	    write!(f, "{:02}:{:02}", mins, secs)
+	}
+    }
+}
+
+/// Formats a count in a `CountUnit`, e.g. `1,234` or `1.2 MiB`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HumanUnit(pub u64, pub CountUnit);
+
+impl fmt::Display for HumanUnit
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+	match self.1 {
+	    CountUnit::Items => fmt::Display::fmt(&HumanCount(self.0), f),
+	    CountUnit::Bytes(units) => fmt::Display::fmt(&HumanBytes(self.0 as f64, units), f),
+	}
+    }
+}
+
+/// Formats a rate per second in a `CountUnit`, e.g. `450 it/s`, `1.2k it/s` or `12.3 MiB/s`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HumanRate(pub f64, pub CountUnit);
+
+impl fmt::Display for HumanRate
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+	let Self(rate, unit) = *self;
+	match unit {
+	    CountUnit::Bytes(units) => write!(f, "{}/s", HumanBytes(rate, units)),
+	    CountUnit::Items if rate >= 1000.0 => {
+		const PREFIXES: [&str; 5] = ["", "k", "M", "G", "T"];
+		let mut rate = rate;
+		let mut prefix = 0;
+		while rate >= 1000.0 && prefix < PREFIXES.len() - 1 {
+		    rate /= 1000.0;
+		    prefix += 1;
+		}
+		write!(f, "{:.1}{} it/s", rate, PREFIXES[prefix])
+	    },
+	    CountUnit::Items if rate >= 100.0 => write!(f, "{:.0} it/s", rate),
+	    CountUnit::Items => write!(f, "{:.1} it/s", rate),
+	}
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    #[test]
+    fn counts()
+    {
+	assert_eq!(HumanCount(0).to_string(), "0");
+	assert_eq!(HumanCount(999).to_string(), "999");
+	assert_eq!(HumanCount(1000).to_string(), "1,000");
+	assert_eq!(HumanCount(1234567).to_string(), "1,234,567");
+    }
+
+    #[test]
+    fn bytes()
+    {
+	assert_eq!(HumanBytes(512.0, ByteUnits::Iec).to_string(), "512 B");
+	assert_eq!(HumanBytes(1536.0, ByteUnits::Iec).to_string(), "1.5 KiB");
+	assert_eq!(HumanBytes(1536.0, ByteUnits::Si).to_string(), "1.5 kB");
+	assert_eq!(format!("{:.2}", HumanBytes(12.345 * 1024.0 * 1024.0, ByteUnits::Iec)), "12.35 MiB");
+	assert_eq!(CountUnit::Bytes(ByteUnits::Si).count(2_000_000).to_string(), "2.0 MB");
+    }
+
+    #[test]
+    fn rates_and_durations()
+    {
+	assert_eq!(CountUnit::Items.rate(450.2).to_string(), "450 it/s");
+	assert_eq!(CountUnit::Items.rate(4.52).to_string(), "4.5 it/s");
+	assert_eq!(CountUnit::Items.rate(1234.0).to_string(), "1.2k it/s");
+	assert_eq!(CountUnit::Bytes(ByteUnits::Iec).rate(12.3 * 1024.0 * 1024.0).to_string(), "12.3 MiB/s");
+	assert_eq!(HumanDuration(Duration::from_secs(65)).to_string(), "01:05");
+	assert_eq!(HumanDuration(Duration::from_secs(3665)).to_string(), "1:01:05");
+    }
+}
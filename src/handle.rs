@@ -0,0 +1,344 @@
+//! A clonable, thread-safe handle for reporting progress to a `progress::Bar` from many threads.
+
+use super::*;
+use progress::Bar;
+use std::{
+    io,
+    sync::{
+	Arc,
+	Mutex,
+	MutexGuard,
+	PoisonError,
+	TryLockError,
+	atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+pub use tick::SteadyTick;
+
+#[derive(Debug)]
+struct Shared<T>
+{
+    position: AtomicU64,
+    length: AtomicU64,
+    /// Set whenever `position` or `length` change, and cleared by the thread that draws the change.
+    dirty: AtomicBool,
+    /// The number of `SteadyTick`s running. While there are any, changes are left for them to draw.
+    ticking: AtomicUsize,
+    bar: Mutex<Bar<T>>,
+}
+
+/// A clonable handle to a `progress::Bar` that many threads can report progress to at once.
+///
+/// Progress is counted as a `position` out of a `length`, both of which are atomics, so updating them never blocks.
+///
+/// # Rendering
+/// Rendering is coalesced: when a thread changes the progress, it draws the bar only if no other thread is currently drawing it, otherwise it leaves the change to be drawn by that thread once it is done.
+/// Many changes made while the bar is being drawn are so drawn at once.
+///
+/// With `steady_tick()`, changes are never drawn by the threads making them, and the bar is instead redrawn at a fixed interval by a background thread.
+///
+/// # Usage
+/// ```rust
+/// # use termprogress::prelude::*;
+/// let bar = Bar::default().into_handle(100);
+/// std::thread::scope(|s| {
+///     for _ in 0..4 {
+///         let bar = bar.clone();
+///         s.spawn(move || for _ in 0..25 {
+///             // *does work*
+///             bar.inc(1);
+///         });
+///     }
+/// });
+/// assert_eq!(bar.position(), 100);
+/// ```
+#[derive(Debug)]
+pub struct BarHandle<T = DefaultOutputDevice>
+{
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Clone for BarHandle<T>
+{
+    #[inline]
+    fn clone(&self) -> Self
+    {
+	Self { shared: Arc::clone(&self.shared) }
+    }
+}
+
+impl<T> BarHandle<T>
+{
+    /// Create a new handle for `bar` with a `length` and a position of 0.
+    ///
+    /// The bar's own position and length are replaced by the handle's.
+    pub fn new(bar: Bar<T>, length: u64) -> Self
+    {
+	Self {
+	    shared: Arc::new(Shared {
+		position: AtomicU64::new(0),
+		length: AtomicU64::new(length),
+		dirty: AtomicBool::new(false),
+		ticking: AtomicUsize::new(0),
+		bar: Mutex::new(bar),
+	    }),
+	}
+    }
+
+    /// The current position.
+    #[inline]
+    pub fn position(&self) -> u64
+    {
+	self.shared.position.load(Ordering::Acquire)
+    }
+
+    /// The current length.
+    #[inline]
+    pub fn length(&self) -> u64
+    {
+	self.shared.length.load(Ordering::Acquire)
+    }
+
+    /// The current progress, `position / length`, clamped to `1.0`.
+    ///
+    /// If the length is 0, the progress is `1.0`.
+    pub fn fraction(&self) -> f64
+    {
+	let (position, length) = (self.position(), self.length());
+	if length == 0 {
+	    1.0
+	} else {
+	    (position as f64 / length as f64).min(1.0)
+	}
+    }
+
+    /// Lock the bar, waiting for any other thread currently drawing it.
+    #[inline]
+    fn lock(&self) -> MutexGuard<'_, Bar<T>>
+    {
+	self.shared.bar.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Bring the locked `bar`'s position and length up to date with the handle's, without redisplaying.
+    ///
+    /// Returns whether they had changed.
+    fn sync(&self, bar: &mut Bar<T>) -> bool
+    {
+	let (position, length) = (self.position(), self.length());
+	let changed = bar.position() != position || bar.length() != Some(length);
+	if changed {
+	    bar.sync_counts(position, length);
+	}
+	changed
+    }
+
+    /// Lock the bar, waiting for any other thread currently drawing it, and call `fun` with it.
+    ///
+    /// The bar's position and length are brought up to date with the handle's first.
+    /// This can be used for operations the handle does not expose itself, e.g. `Display::set_title()`.
+    #[inline]
+    pub fn with_bar<F, U>(&self, fun: F) -> U
+    where F: FnOnce(&mut Bar<T>) -> U
+    {
+	let mut bar = self.lock();
+	self.sync(&mut bar);
+	fun(&mut bar)
+    }
+
+    /// Consume the handle, returning the bar if this is the last handle to it.
+    ///
+    /// The bar's position and length are brought up to date with the handle's, without redisplaying.
+    /// Otherwise, `Err(self)` is returned.
+    pub fn try_into_bar(self) -> Result<Bar<T>, Self>
+    {
+	self.sync(&mut self.lock());
+	Arc::try_unwrap(self.shared)
+	    .map(|shared| shared.bar.into_inner().unwrap_or_else(PoisonError::into_inner))
+	    .map_err(|shared| Self { shared })
+    }
+}
+
+impl<T: io::Write> BarHandle<T>
+{
+    /// Advance the position by `n`.
+    #[inline]
+    pub fn inc(&self, n: u64)
+    {
+	self.shared.position.fetch_add(n, Ordering::AcqRel);
+	self.changed();
+    }
+
+    /// Set the position.
+    #[inline]
+    pub fn set_position(&self, position: u64)
+    {
+	self.shared.position.store(position, Ordering::Release);
+	self.changed();
+    }
+
+    /// Set the length.
+    #[inline]
+    pub fn set_length(&self, length: u64)
+    {
+	self.shared.length.store(length, Ordering::Release);
+	self.changed();
+    }
+
+    /// Set the position to the length, and draw the bar, waiting for any other thread currently drawing it.
+    pub fn finish(&self)
+    {
+	self.set_position(self.length());
+	self.force_draw();
+    }
+
+    /// Draw the bar's current progress, regardless of its draw interval, waiting for any other thread currently drawing it.
+    pub fn force_draw(&self)
+    {
+	let mut bar = self.lock();
+	self.shared.dirty.store(false, Ordering::Release);
+	self.draw(&mut bar, true);
+    }
+
+    /// Redraw the bar every `interval` on a background thread, until the returned `SteadyTick` is dropped.
+    ///
+    /// While it runs, changing the progress through any handle only updates the atomic position and length, and leaves drawing to the ticks.
+    /// This makes updates from hot loops cheap, and keeps the `{spinner}`, `{elapsed}` and `{eta}` moving while the progress is not.
+    ///
+    /// The thread holds a handle to the bar, so `try_into_bar()` fails until the `SteadyTick` is dropped.
+    pub fn steady_tick(&self, interval: Duration) -> SteadyTick
+    where T: Send + 'static
+    {
+	self.shared.ticking.fetch_add(1, Ordering::AcqRel);
+	let (ticking, stopping) = (self.clone(), self.clone());
+	SteadyTick::spawn(interval, move || ticking.force_draw(), move || {
+	    stopping.shared.ticking.fetch_sub(1, Ordering::AcqRel);
+	    // Draw anything changed since the last tick.
+	    if stopping.shared.dirty.load(Ordering::Acquire) {
+		stopping.force_draw();
+	    }
+	})
+    }
+
+    /// Blank then print a line, and redisplay, waiting for any other thread currently drawing the bar.
+    #[inline]
+    pub fn println(&self, string: &str)
+    {
+	self.lock().println(string);
+    }
+
+    /// Copy the position and length to the locked `bar`, and draw them if they have changed since the last draw (or if `force` is set.)
+    fn draw(&self, bar: &mut Bar<T>, force: bool)
+    {
+	let changed = self.sync(bar);
+	if force {
+	    bar.tick();
+	} else if changed {
+	    bar.refresh_mut();
+	}
+    }
+
+    /// The progress has changed, draw it unless another thread is already drawing, or a `SteadyTick` will.
+    fn changed(&self)
+    {
+	self.shared.dirty.store(true, Ordering::Release);
+	if self.shared.ticking.load(Ordering::Acquire) > 0 {
+	    return;
+	}
+	// If another thread is drawing while we set `dirty`, it will see it once it has finished and draw again.
+	while self.shared.dirty.load(Ordering::Acquire) {
+	    let mut bar = match self.shared.bar.try_lock() {
+		Ok(bar) => bar,
+		Err(TryLockError::Poisoned(bar)) => bar.into_inner(),
+		Err(TryLockError::WouldBlock) => return,
+	    };
+	    if !self.shared.dirty.swap(false, Ordering::AcqRel) {
+		return;
+	    }
+	    self.draw(&mut bar, false);
+	}
+    }
+}
+
+impl<T> Bar<T>
+{
+    /// Convert into a clonable, thread-safe handle with a `length` and a position of 0.
+    ///
+    /// See `handle::BarHandle`.
+    #[inline]
+    pub fn into_handle(self, length: u64) -> BarHandle<T>
+    {
+	BarHandle::new(self, length)
+    }
+}
+
+const _: () = {
+    fn assert_send_sync<T: Send + Sync + Clone>() {}
+    fn test()
+    {
+	assert_send_sync::<BarHandle<io::Stdout>>();
+	assert_send_sync::<BarHandle<std::fs::File>>();
+    }
+};
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    fn handle(length: u64) -> (testing::VirtualTerminal, BarHandle<testing::VirtualTerminal>)
+    {
+	let term = testing::VirtualTerminal::new(60, 3);
+	(term.clone(), Bar::with_size(term.clone(), 10, term).into_handle(length))
+    }
+
+    #[test]
+    fn counting()
+    {
+	let (term, bar) = handle(1_000_000);
+	for _ in 0..50 {
+	    bar.inc(1);
+	}
+	assert_eq!(bar.with_bar(|bar| bar.position()), 50);
+	assert_eq!(term.screen()[0], "[          ]: 0.01% 50/1,000,000");
+
+	// Left for the thread holding the bar, which never draws it.
+	bar.with_bar(|_| bar.inc(7));
+	let bar = bar.try_into_bar().unwrap();
+	assert_eq!((bar.position(), bar.length()), (57, Some(1_000_000)));
+    }
+
+    #[test]
+    fn coalescing()
+    {
+	let (term, bar) = handle(1000);
+	bar.set_position(10);
+	bar.with_bar(|_| std::thread::scope(|s| {
+	    s.spawn(|| for _ in 0..100 {
+		bar.inc(1);
+	    });
+	    s.spawn(|| bar.set_length(2000));
+	}));
+	assert_eq!(term.screen()[0], "[          ]: 1.00% 10/1,000", "nothing is drawn while the bar is locked");
+	bar.inc(0);
+	assert_eq!(term.screen()[0], "[          ]: 5.50% 110/2,000");
+    }
+
+    #[test]
+    fn many_threads()
+    {
+	let (term, bar) = handle(8000);
+	std::thread::scope(|s| {
+	    for _ in 0..8 {
+		let bar = bar.clone();
+		s.spawn(move || for _ in 0..1000 {
+		    bar.inc(1);
+		});
+	    }
+	});
+	assert_eq!(bar.position(), 8000);
+	assert_eq!(term.screen()[0], "[==========]: 100.00% 8,000/8,000");
+	let bar = bar.try_into_bar().unwrap();
+	assert_eq!((bar.position(), bar.length()), (8000, Some(8000)));
+    }
+}
@@ -1,3 +1,5 @@
+use std::io;
+
 /// A trait for all bars' displaying
 pub trait Display
 {
@@ -6,7 +8,16 @@ pub trait Display
     /// Blank the display
     fn blank(&self);
     /// Blank then print a line, and redisplay.
+    ///
+    /// `progress::Bar` and `spinner::Spin` print the line to their own output, so it always lands on the same stream as the indicator. By default, this is `println_stdout()`.
     fn println(&self, string: &str)
+    {
+	self.println_stdout(string);
+    }
+    /// Blank then print a line to stdout (with `println!`), and redisplay.
+    ///
+    /// The line is printed to stdout whichever stream the indicator draws to.
+    fn println_stdout(&self, string: &str)
     {
 	self.blank();
 	println!("{}", string);
@@ -19,6 +30,13 @@ pub trait Display
 	eprintln!("{}", string);
 	self.refresh();
     }
+    /// An `io::Write` that prints each line written to it with `println()`, see `PrintWriter`.
+    #[inline]
+    fn print_writer(&self) -> PrintWriter<'_, Self>
+    where Self: Sized
+    {
+	PrintWriter::new(self)
+    }
 
     /// Get the title for this display
     fn get_title(&self) -> &str;
@@ -61,8 +79,81 @@ pub trait WithTitle: Sized + Display
     fn complete(self);
 }
 
+/// An `io::Write` that prints each line written to it above an indicator with `Display::println()`, blanking and redrawing the indicator around each one.
+///
+/// This lets anything that writes to an `io::Write` (e.g. a logger, or `writeln!()`) print without breaking up the indicator's line.
+/// Bytes after the last newline are held until the line is finished, or the writer is dropped. Lines that are not valid UTF-8 are printed lossily.
+///
+/// ```rust
+/// # use termprogress::prelude::*;
+/// # use std::io::Write;
+/// let bar: Bar<Vec<u8>> = Bar::with_size(Vec::new(), 20, termprogress::size::FixedSize(80));
+/// writeln!(bar.print_writer(), "copied {} files", 3).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct PrintWriter<'a, D: ?Sized + Display>
+{
+    display: &'a D,
+    line: Vec<u8>,
+}
+
+impl<'a, D: ?Sized + Display> PrintWriter<'a, D>
+{
+    /// Print the lines written to this above `display`.
+    #[inline]
+    pub fn new(display: &'a D) -> Self
+    {
+	Self {
+	    display,
+	    line: Vec::new(),
+	}
+    }
+
+    fn print(&self, line: &[u8])
+    {
+	self.display.println(&String::from_utf8_lossy(line));
+    }
+}
+
+impl<D: ?Sized + Display> io::Write for PrintWriter<'_, D>
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize>
+    {
+	let mut rest = buf;
+	while let Some(end) = rest.iter().position(|&b| b == b'\n') {
+	    if self.line.is_empty() {
+		self.print(&rest[..end]);
+	    } else {
+		self.line.extend_from_slice(&rest[..end]);
+		self.print(&self.line);
+		self.line.clear();
+	    }
+	    rest = &rest[end + 1..];
+	}
+	self.line.extend_from_slice(rest);
+	Ok(buf.len())
+    }
+
+    /// Lines are printed as soon as they are finished, so there is nothing to flush.
+    #[inline]
+    fn flush(&mut self) -> io::Result<()>
+    {
+	Ok(())
+    }
+}
+
+impl<D: ?Sized + Display> Drop for PrintWriter<'_, D>
+{
+    fn drop(&mut self)
+    {
+	if !self.line.is_empty() {
+	    self.print(&self.line);
+	}
+    }
+}
+
 impl<T> WithTitle for Box<T>
-where T: WithTitle + ?Sized
+where T: WithTitle
 {
     /*fn with_title(len: usize, string: impl AsRef<str>) -> Self
     {
@@ -97,6 +188,10 @@ where T: Display + ?Sized
     {
 	self.as_ref().println(string);
     }
+    #[inline] fn println_stdout(&self, string: &str)
+    {
+	self.as_ref().println_stdout(string);
+    }
     #[inline] fn eprintln(&self, string: &str)
     {
 	self.as_ref().eprintln(string);
@@ -152,6 +247,10 @@ impl Display for std::convert::Infallible
     #[inline] fn println(&self, _: &str)
     {
 	
+    }
+    #[inline] fn println_stdout(&self, _: &str)
+    {
+	
     }
     #[inline] fn eprintln(&self, _: &str)
     {
@@ -206,6 +305,10 @@ impl Spinner for std::convert::Infallible
 	#[inline] fn println(&self, _: &str)
 	{
 	    
+	}
+	#[inline] fn println_stdout(&self, _: &str)
+	{
+	    
 	}
 	#[inline] fn eprintln(&self, _: &str)
 	{
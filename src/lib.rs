@@ -2,7 +2,7 @@
 
 #![allow(dead_code)]
 
-//TODO: XXX: Change default output to `stderr`, **NOT** stdout, ffs... Also add allow custom stream output.  Change behaviour that if `not isatty(S)` with `terminal_size` feature enabled an error is returned instead of *guessing* default sizes when it's not (caller can force by `unwrap_or*(50)`.)
+//TODO: Change behaviour that if `not isatty(S)` with `terminal_size` feature enabled an error is returned instead of *guessing* default sizes when it's not (caller can force by `unwrap_or*(50)`.)
 
 macro_rules! flush {
     ($stream:expr) => {
@@ -29,14 +29,11 @@ macro_rules! flush {
     };
 }
 
-/// The default place to write bars to if an output is not user-specified.
-pub(crate) type DefaultOutputDevice = std::io::Stdout;
-
 /// A function that creates the default output device object for constructing a progress bar.
 ///
 /// This must return multiple handles, since multiple bars can exist throughout the program at overlapping lifetimes.
 /// `DefaultOutputDevice` should internally manage this state.
-pub(crate) const CREATE_DEFAULT_OUTPUT_DEVICE_FUNC: fn () -> DefaultOutputDevice = std::io::stdout;
+pub(crate) const CREATE_DEFAULT_OUTPUT_DEVICE_FUNC: fn () -> DefaultOutputDevice = DefaultOutputDevice::default;
 
 /// Create an object for the default output device.
 #[inline] 
@@ -45,35 +42,51 @@ pub(crate) fn create_default_output_device() -> DefaultOutputDevice
     CREATE_DEFAULT_OUTPUT_DEVICE_FUNC()
 }
 
-#[cfg(feature="size")]
-#[inline(always)] 
-fn terminal_size_of(f: &(impl AsFd + ?Sized)) -> Option<(terminal_size::Width, terminal_size::Height)>
-{
-    terminal_size::terminal_size_of(f)
-}
-
 use atomic_refcell::AtomicRefCell;
 
-//#[cfg(feature="size")] TODO: How to add `AsRawFd` bound to `Bar` *only* when `size` feature is enabled?
-//use std::os::unix::io::*; // Not currently needed right now, platform-agnostic `AsFd` is used instead.
 use std::os::fd::AsFd;
 
 mod util;
 mod inter;
 pub use inter::*;
 
+pub mod error;
+pub use error::ErrorPolicy;
+
+pub mod output;
+pub use output::{
+    DefaultOutputDevice,
+    OutputStream,
+    set_default_output_stream,
+    default_output_stream,
+};
+
 pub mod progress;
 pub mod wheel;
 pub mod spinner;
 pub mod silent;
+pub mod multi;
+pub mod handle;
+pub mod tick;
+pub mod cleanup;
+pub mod estimate;
+pub mod format;
+pub mod template;
+pub mod style;
+pub mod color;
+pub mod width;
+pub mod lines;
+pub mod size;
+#[cfg(any(test, feature="test-support"))] pub mod testing;
+#[cfg(reactive)] pub mod reactive;
 
-/// Returns true if `stdout` has a terminal output and can be used with terminal size responsiveness.
+/// Returns true if the default output stream (see `default_output_stream()`) has a terminal output and can be used with terminal size responsiveness.
 ///
 /// Requires `size` feature.
 #[cfg(feature="size")] 
 pub fn has_terminal_output_default() -> bool
 {
-    terminal_size::terminal_size().is_some()
+    has_terminal_output(&create_default_output_device())
 }
 
 /// Returns true if `f` has a terminal output and can be used with terminal size responsiveness.
@@ -0,0 +1,160 @@
+//! Logging progress as plain lines, for output that is not a terminal.
+//!
+//! When a `progress::Bar` draws to a file, a pipe, or a CI log, redrawing the line in place with `\r` and escape codes just fills the log with garbage.
+//! Instead, in `DrawMode::Lines` the bar prints a line like `title: 40%` every time its progress passes a percentage step or a time interval (see `Interval`), and once more when it is completed.
+
+use std::{
+    env,
+    time::{
+	Duration,
+	Instant,
+    },
+};
+
+/// How an indicator draws itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DrawMode
+{
+    /// Decide when first drawn, see `DrawMode::detect()`.
+    #[default]
+    Auto,
+    /// Redraw the line in place.
+    Terminal,
+    /// Print progress as plain lines at intervals, without any escape codes.
+    Lines,
+}
+
+impl DrawMode
+{
+    /// The mode to use for an output, which `is_terminal` or not: `Lines` if it is not a terminal or the `CI` environment variable is set, `Terminal` otherwise.
+    ///
+    /// This is what a bar drawing to a file descriptor is drawn in by default, see `size::SizeQuery::draw_mode()`.
+    pub fn detect(is_terminal: bool) -> Self
+    {
+	if env::var_os("CI").is_some_and(|v| !v.is_empty()) || !is_terminal {
+	    Self::Lines
+	} else {
+	    Self::Terminal
+	}
+    }
+}
+
+/// How often progress is printed in `DrawMode::Lines`.
+///
+/// A line is printed whenever the progress passes a multiple of `percent`, or when `time` has passed since the last line was printed and the progress has changed.
+/// The final progress is always printed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval
+{
+    /// The percentage steps to print at, e.g. `10.0` prints at most 11 lines for the percentages alone (`0%`, `10%`, ... `100%`.)
+    ///
+    /// If this is `0.0` or less, every change is printed.
+    pub percent: f64,
+    /// The longest time to go without printing a change, if any.
+    pub time: Option<Duration>,
+}
+
+impl Default for Interval
+{
+    #[inline]
+    fn default() -> Self
+    {
+	Self {
+	    percent: 10.0,
+	    time: Some(Duration::from_secs(30)),
+	}
+    }
+}
+
+impl Interval
+{
+    /// The step `progress` (in `0..=1`) is in.
+    #[inline]
+    fn step(&self, progress: f64) -> f64
+    {
+	if self.percent > 0.0 {
+	    (progress * 100.0 / self.percent).floor()
+	} else {
+	    progress
+	}
+    }
+}
+
+/// Decides when to print progress in `DrawMode::Lines`.
+#[derive(Debug, Clone)]
+pub(crate) struct LineLog
+{
+    pub(crate) interval: Interval,
+    /// The time and progress of the last line printed.
+    last: Option<(Instant, f64)>,
+}
+
+impl LineLog
+{
+    #[inline]
+    pub(crate) fn new(interval: Interval) -> Self
+    {
+	Self {
+	    interval,
+	    last: None,
+	}
+    }
+
+    /// Should `progress` be printed at `now`? If so, it is remembered as the last line printed.
+    pub(crate) fn due(&mut self, progress: f64, now: Instant) -> bool
+    {
+	let due = match self.last {
+	    None => true,
+	    Some((_, last)) if last == progress => false,
+	    Some((then, last)) => progress >= 1.0
+		|| self.interval.step(progress) != self.interval.step(last)
+		|| self.interval.time.is_some_and(|time| now.saturating_duration_since(then) >= time),
+	};
+	if due {
+	    self.last = Some((now, progress));
+	}
+	due
+    }
+
+    /// Should `progress` be printed on completion? It is unless it was the last line printed.
+    #[inline]
+    pub(crate) fn due_final(&self, progress: f64) -> bool
+    {
+	self.last.is_none_or(|(_, last)| last != progress)
+    }
+}
+
+impl Default for LineLog
+{
+    #[inline]
+    fn default() -> Self
+    {
+	Self::new(Default::default())
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    #[test]
+    fn intervals()
+    {
+	let start = Instant::now();
+	let mut log = LineLog::new(Interval { percent: 25.0, time: Some(Duration::from_secs(10)) });
+	let printed: Vec<_> = (0..=100)
+	    .map(|i| i as f64 / 100.0)
+	    .filter(|&p| log.due(p, start))
+	    .collect();
+	assert_eq!(printed, [0.0, 0.25, 0.5, 0.75, 1.0]);
+	assert!(!log.due_final(1.0));
+
+	let mut log = LineLog::new(Interval { percent: 25.0, time: Some(Duration::from_secs(10)) });
+	assert!(log.due(0.01, start));
+	assert!(!log.due(0.02, start + Duration::from_secs(5)));
+	assert!(log.due(0.03, start + Duration::from_secs(11)));
+	assert!(!log.due(0.03, start + Duration::from_secs(30)));
+	assert!(log.due_final(0.04));
+    }
+}
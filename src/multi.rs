@@ -0,0 +1,653 @@
+//! Drawing multiple indicators on consecutive lines at once.
+//!
+//! A `MultiProgress` owns an output device and draws a block of rows to it, one for each indicator added to it.
+//! Indicators in a row are ordinary `progress::Bar`s and `spinner::Spin`s whose output device is a `Row`, which captures their rendered line and redraws the whole block.
+//! They take the size of the terminal from the block's `size::SizeQuery`, so the block can draw to any `io::Write` (see `MultiProgress::with_size()`.)
+//!
+//! # Usage
+//! ```rust
+//! # use termprogress::{prelude::*, multi::MultiProgress};
+//! let multi: MultiProgress = MultiProgress::new_default().unwrap();
+//! let mut download = multi.add_bar(50);
+//! let mut unpack = multi.add_spin(Default::default());
+//! download.set_progress(0.5);
+//! unpack.bump();
+//! multi.println("Something happened"); // Printed above the block.
+//! download.complete().unwrap(); // The bar's last line is printed above the block, and its row is removed.
+//! drop(unpack); // The spinner's last line is printed above the block, and its row is removed.
+//! ```
+//!
+//! An indicator that is dropped without being completed is still finished by its `progress::FinishMode`, so its last line is printed above the block unless the mode is `FinishMode::Clear`.
+//!
+//! The cursor is hidden while any indicator in the block hides it (see `progress::Bar::with_hide_cursor()`.)
+
+use super::*;
+use error::ErrorState;
+use std::{
+    fmt::Write as _,
+    io,
+    os::fd::AsFd,
+    sync::{
+	Arc,
+	Mutex,
+	MutexGuard,
+	PoisonError,
+	atomic::{AtomicU64, Ordering},
+    },
+};
+
+/// A single rendered cell of a row: a character and any escape sequences written before it.
+#[derive(Debug, Clone, Default)]
+struct Cell
+{
+    escapes: String,
+    ch: char,
+}
+
+/// The contents of a row, as written by its indicator.
+#[derive(Debug, Clone, Default)]
+struct Line
+{
+    cells: Vec<Cell>,
+    cursor: usize,
+    /// Escape sequences written after the last cell.
+    escapes: String,
+}
+
+impl Line
+{
+    fn put(&mut self, ch: char)
+    {
+	let cell = Cell { escapes: std::mem::take(&mut self.escapes), ch };
+	if self.cursor < self.cells.len() {
+	    self.cells[self.cursor] = cell;
+	} else {
+	    self.cells.push(cell);
+	}
+	self.cursor += 1;
+    }
+
+    fn render_into(&self, to: &mut String)
+    {
+	for cell in self.cells.iter() {
+	    to.push_str(&cell.escapes);
+	    to.push(cell.ch);
+	}
+	to.push_str(&self.escapes);
+    }
+}
+
+/// Interpret the bytes an indicator wrote for a frame, updating its row's `line`.
+///
+/// Any lines that were completed by a newline are appended to `finished`, and `hides_cursor` is set while the indicator has hidden the cursor.
+/// Only the control sequences written by the indicators in this crate are understood.
+fn interpret(frame: &[u8], line: &mut Line, finished: &mut Vec<Line>, hides_cursor: &mut bool)
+{
+    let frame = String::from_utf8_lossy(frame);
+    // Lines finished in *this* frame, that may be moved back up into.
+    let mut scrolled = 0usize;
+    let mut chars = frame.chars().peekable();
+    while let Some(ch) = chars.next() {
+	match ch {
+	    '\r' => line.cursor = 0,
+	    '\n' => {
+		finished.push(std::mem::take(line));
+		scrolled += 1;
+	    },
+	    '\x08' => line.cursor = line.cursor.saturating_sub(1),
+	    '\x1B' if chars.peek() == Some(&'[') => {
+		chars.next();
+		let mut params = String::new();
+		let mut command = None;
+		for c in chars.by_ref() {
+		    if c.is_ascii_alphabetic() {
+			command = Some(c);
+			break;
+		    }
+		    params.push(c);
+		}
+		match command {
+		    Some('K') => line.cells.truncate(line.cursor),
+		    Some('A') => {
+			for _ in 0..params.parse().unwrap_or(1usize).min(scrolled) {
+			    *line = finished.pop().unwrap_or_default();
+			    line.cursor = 0;
+			    scrolled -= 1;
+			}
+		    },
+		    Some('m') => {
+			line.escapes.push_str("\x1B[");
+			line.escapes.push_str(&params);
+			line.escapes.push('m');
+		    },
+		    Some('l') if params == "?25" => *hides_cursor = true,
+		    Some('h') if params == "?25" => *hides_cursor = false,
+		    // Anything else would move the cursor outside of our row.
+		    _ => (),
+		}
+	    },
+	    ch if ch.is_control() => (),
+	    ch => line.put(ch),
+	}
+    }
+}
+
+/// A row of the block.
+#[derive(Debug, Default)]
+struct Slot
+{
+    id: u64,
+    line: Line,
+    /// Has the row's indicator hidden the cursor?
+    hides_cursor: bool,
+}
+
+#[derive(Debug)]
+struct Inner<T>
+{
+    rows: Vec<Slot>,
+    /// Lines to print above the block on the next draw.
+    above: Vec<String>,
+    /// The number of rows drawn on the last draw.
+    drawn: usize,
+    /// Is the cursor of the output currently hidden?
+    cursor_hidden: bool,
+    /// The block's place in the panic hook's list, if it is drawn to a terminal (see `cleanup`.)
+    registration: Option<cleanup::Registration>,
+    output: T,
+}
+
+impl<T: io::Write> Inner<T>
+{
+    /// Move to the top of the block and clear it.
+    fn clear_into(&mut self, frame: &mut String)
+    {
+	if self.drawn > 0 {
+	    write!(frame, "\r\x1B[{}A", self.drawn).unwrap();
+	}
+	frame.push_str("\r\x1B[J");
+	self.drawn = 0;
+    }
+
+    /// Redraw the whole block, printing any pending lines above it first.
+    ///
+    /// The cursor is hidden while any row hides it, and shown again once none does.
+    fn draw(&mut self) -> io::Result<()>
+    {
+	let hide = self.rows.iter().any(|slot| slot.hides_cursor);
+	let mut frame = String::new();
+	if hide && !self.cursor_hidden {
+	    frame.push_str(cleanup::HIDE_CURSOR);
+	}
+	self.clear_into(&mut frame);
+	for line in self.above.drain(..) {
+	    frame.push_str(&line);
+	    frame.push_str("\x1B[0m\n");
+	}
+	for slot in self.rows.iter() {
+	    slot.line.render_into(&mut frame);
+	    frame.push_str("\x1B[0m\n");
+	}
+	self.drawn = self.rows.len();
+	if !hide && self.cursor_hidden {
+	    frame.push_str(cleanup::SHOW_CURSOR);
+	}
+	self.cursor_hidden = hide;
+	self.write(&frame)
+    }
+
+    fn blank(&mut self) -> io::Result<()>
+    {
+	let mut frame = String::new();
+	self.clear_into(&mut frame);
+	self.write(&frame)
+    }
+
+    /// Write a frame to the output, unless the panic hook has already restored the terminal.
+    fn write(&mut self, frame: &str) -> io::Result<()>
+    {
+	if let Some(registration) = &self.registration {
+	    if registration.is_cleaned() {
+		return Ok(());
+	    }
+	    registration.activate();
+	}
+	self.output.write_all(frame.as_bytes())
+	    .and_then(|_| flush!(? self.output))
+    }
+
+    /// Apply a frame written by row `id`.
+    fn apply(&mut self, id: u64, frame: &[u8]) -> io::Result<()>
+    {
+	let Some(slot) = self.rows.iter_mut().find(|slot| slot.id == id) else { return Ok(()) };
+	let mut finished = Vec::new();
+	interpret(frame, &mut slot.line, &mut finished, &mut slot.hides_cursor);
+	self.above.extend(finished.into_iter().map(|line| {
+	    let mut string = String::new();
+	    line.render_into(&mut string);
+	    string
+	}));
+	self.draw()
+    }
+}
+
+#[derive(Debug)]
+struct Shared<T>
+{
+    inner: Mutex<Inner<T>>,
+    /// The size of the terminal the block is drawn to, which the indicators in rows query.
+    size: Arc<dyn size::SizeQuery>,
+    next_id: AtomicU64,
+}
+
+impl<T> Shared<T>
+{
+    #[inline]
+    fn lock(&self) -> MutexGuard<'_, Inner<T>>
+    {
+	// A panic while drawing leaves nothing in an invalid state.
+	self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A block of rows on consecutive lines, each drawn by an indicator.
+///
+/// Rows are drawn in the order they were added. Lines printed with `println()` (or by an indicator in a row, e.g. with `Spin::println()`) scroll above the block.
+///
+/// # Thread `Sync`safety
+/// Indicators in rows can be moved to and rendered from different threads. Unlike with a single indicator, display operations on the block are never aborted, instead they wait for each other.
+#[derive(Debug)]
+pub struct MultiProgress<T = DefaultOutputDevice>
+{
+    shared: Arc<Shared<T>>,
+    errors: ErrorState,
+}
+
+impl MultiProgress
+{
+    /// Create a new empty block drawing to the default output stream (see `default_output_stream()`).
+    ///
+    /// # Errors
+    /// If the output device's descriptor cannot be duplicated to query its terminal size.
+    #[inline]
+    pub fn new_default() -> io::Result<Self>
+    {
+	Self::new(create_default_output_device())
+    }
+}
+
+impl<T: io::Write + AsFd> MultiProgress<T>
+{
+    /// Create a new empty block drawing to `output`.
+    ///
+    /// # Errors
+    /// If the output device's descriptor cannot be duplicated to query its terminal size.
+    pub fn new(output: T) -> io::Result<Self>
+    {
+	let size = size::FdSize::new(&output)?;
+	let registration = cleanup::Registration::new(&output);
+	Ok(Self::with_registration(output, Arc::new(size), registration))
+    }
+}
+
+impl<T: io::Write> MultiProgress<T>
+{
+    /// Create a new empty block drawing to any `output`, where the size of the terminal (if there is one) is queried from `size`.
+    ///
+    /// See `progress::Bar::with_size()`.
+    #[inline]
+    pub fn with_size(output: T, size: impl size::SizeQuery + 'static) -> Self
+    {
+	Self::with_registration(output, Arc::new(size), None)
+    }
+
+    fn with_registration(output: T, size: Arc<dyn size::SizeQuery>, registration: Option<cleanup::Registration>) -> Self
+    {
+	Self {
+	    shared: Arc::new(Shared {
+		inner: Mutex::new(Inner {
+		    rows: Vec::new(),
+		    above: Vec::new(),
+		    drawn: 0,
+		    cursor_hidden: false,
+		    registration,
+		    output,
+		}),
+		size,
+		next_id: AtomicU64::new(0),
+	    }),
+	    errors: Default::default(),
+	}
+    }
+
+    /// Add a new empty row to the bottom of the block, returning the output device for the indicator to draw to it.
+    pub fn add_row(&self) -> Row<T>
+    {
+	let id = self.shared.next_id.fetch_add(1, Ordering::Relaxed);
+	let mut inner = self.shared.lock();
+	inner.rows.push(Slot { id, ..Default::default() });
+	self.errors.handle(inner.draw());
+	Row {
+	    shared: Arc::clone(&self.shared),
+	    id,
+	    frame: Vec::new(),
+	}
+    }
+
+    /// Add a new `progress::Bar` `width` long to the bottom of the block.
+    ///
+    /// See `progress::Bar::new()`.
+    #[inline]
+    pub fn add_bar(&self, width: usize) -> progress::Bar<Row<T>>
+    {
+	progress::Bar::with_size(self.add_row(), width, RowSize(Arc::clone(&self.shared.size)))
+    }
+
+    /// Add a new `spinner::Spin` to the bottom of the block.
+    ///
+    /// See `spinner::Spin::with_size()`.
+    #[inline]
+    pub fn add_spin(&self, whl: wheel::Wheel) -> spinner::Spin<Row<T>>
+    {
+	spinner::Spin::with_size(self.add_row(), "", whl, RowSize(Arc::clone(&self.shared.size)))
+    }
+
+    /// The number of rows in the block.
+    #[inline]
+    pub fn len(&self) -> usize
+    {
+	self.shared.lock().rows.len()
+    }
+
+    /// Is the block empty?
+    #[inline]
+    pub fn is_empty(&self) -> bool
+    {
+	self.len() == 0
+    }
+
+    /// Set the policy for handling I/O errors that occur during infallible display operations on the block.
+    ///
+    /// Errors that occur when an indicator in a row draws are handled by that indicator's policy.
+    #[inline]
+    pub fn set_error_policy(&mut self, policy: ErrorPolicy)
+    {
+	self.errors.set_policy(policy);
+    }
+
+    /// Take the most recent I/O error that occured during a display operation on the block.
+    #[inline]
+    pub fn take_error(&self) -> Option<io::Error>
+    {
+	self.errors.take()
+    }
+
+    /// Redraw the whole block, returning any I/O error that occurs.
+    #[inline]
+    pub fn try_refresh(&self) -> io::Result<()>
+    {
+	self.shared.lock().draw()
+    }
+
+    /// Clear the whole block, returning any I/O error that occurs.
+    ///
+    /// The block is drawn again on the next display operation.
+    #[inline]
+    pub fn try_blank(&self) -> io::Result<()>
+    {
+	self.shared.lock().blank()
+    }
+
+    /// Print a line above the block, returning any I/O error that occurs.
+    pub fn try_println(&self, string: &str) -> io::Result<()>
+    {
+	let mut inner = self.shared.lock();
+	inner.above.extend(string.lines().map(ToOwned::to_owned));
+	inner.draw()
+    }
+}
+
+impl<T: io::Write> Display for MultiProgress<T>
+{
+    #[inline]
+    fn refresh(&self)
+    {
+	self.errors.handle(self.try_refresh());
+    }
+    #[inline]
+    fn blank(&self)
+    {
+	self.errors.handle(self.try_blank());
+    }
+    #[inline]
+    fn println(&self, string: &str)
+    {
+	self.errors.handle(self.try_println(string));
+    }
+
+    /// A block has no title of its own.
+    #[inline]
+    fn get_title(&self) -> &str
+    {
+	""
+    }
+    #[inline]
+    fn set_title(&mut self, _: &str){}
+    #[inline]
+    fn update_dimensions(&mut self, _: usize){}
+}
+
+/// The output device of an indicator drawn in a row of a `MultiProgress`.
+///
+/// Every flushed write is interpreted as a frame of the row's indicator, after which the whole block is redrawn.
+/// Lines completed by the indicator (e.g. on `complete()`) are printed above the block.
+///
+/// When the `Row` is dropped, it is removed from the block.
+#[derive(Debug)]
+pub struct Row<T: io::Write = DefaultOutputDevice>
+{
+    shared: Arc<Shared<T>>,
+    id: u64,
+    frame: Vec<u8>,
+}
+
+impl<T: io::Write> io::Write for Row<T>
+{
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize>
+    {
+	self.frame.extend_from_slice(buf);
+	Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()>
+    {
+	if self.frame.is_empty() {
+	    return Ok(());
+	}
+	let res = self.shared.lock().apply(self.id, &self.frame);
+	self.frame.clear();
+	res
+    }
+}
+
+/// The size of the terminal an indicator in a row draws to, which is the block's.
+#[derive(Debug)]
+struct RowSize(Arc<dyn size::SizeQuery>);
+
+impl size::SizeQuery for RowSize
+{
+    #[inline]
+    fn width(&self) -> Option<usize>
+    {
+	self.0.width()
+    }
+    #[inline]
+    fn is_terminal(&self) -> bool
+    {
+	self.0.is_terminal()
+    }
+    #[inline]
+    fn draw_mode(&self) -> lines::DrawMode
+    {
+	self.0.draw_mode()
+    }
+    #[inline]
+    fn invalidate(&self)
+    {
+	self.0.invalidate();
+    }
+}
+
+impl<T: io::Write> Drop for Row<T>
+{
+    fn drop(&mut self)
+    {
+	let mut inner = self.shared.lock();
+	if !self.frame.is_empty() {
+	    let _ = inner.apply(self.id, &self.frame);
+	}
+	inner.rows.retain(|slot| slot.id != self.id);
+	let _ = inner.draw();
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    fn interpret_str(frames: &[&str]) -> (String, Vec<String>)
+    {
+	let mut line = Line::default();
+	let mut finished = Vec::new();
+	for frame in frames {
+	    interpret(frame.as_bytes(), &mut line, &mut finished, &mut false);
+	}
+	let render = |line: &Line| {
+	    let mut string = String::new();
+	    line.render_into(&mut string);
+	    string
+	};
+	(render(&line), finished.iter().map(render).collect())
+    }
+
+    #[test]
+    fn interpreting_frames()
+    {
+	// `progress::Bar`
+	assert_eq!(interpret_str(&["\x1B[0m\x1B[Kfirst\n\x1B[1A", "\x1B[0m\x1B[Ksecond\n\x1B[1A"]),
+		   ("\x1B[0msecond".to_owned(), vec![]));
+	assert_eq!(interpret_str(&["\x1B[0m\x1B[Kdone\n\x1B[1A", "\n"]),
+		   (String::new(), vec!["\x1B[0mdone".to_owned()]));
+	// `spinner::Spin`
+	assert_eq!(interpret_str(&["\rtitle /", "\rtitle -"]), ("title -".to_owned(), vec![]));
+	assert_eq!(interpret_str(&["\rtitle /", "\r       \rmessage\n\rtitle /"]),
+		   ("title /".to_owned(), vec!["message".to_owned()]));
+	assert_eq!(interpret_str(&["\rtitle /", "\x08Done!\n"]),
+		   (String::new(), vec!["title Done!".to_owned()]));
+
+	let (mut line, mut hides_cursor) = (Line::default(), false);
+	interpret(b"\x1B[?25l\x1B[Kbar\n\x1B[1A", &mut line, &mut Vec::new(), &mut hides_cursor);
+	assert!(hides_cursor);
+	interpret(b"\x1B[Kbar\n\x1B[?25h", &mut line, &mut Vec::new(), &mut hides_cursor);
+	assert!(!hides_cursor);
+    }
+
+    fn block(height: usize) -> (testing::VirtualTerminal, MultiProgress<testing::VirtualTerminal>)
+    {
+	let term = testing::VirtualTerminal::new(40, height);
+	(term.clone(), MultiProgress::with_size(term.clone(), term))
+    }
+
+    #[test]
+    fn redrawing()
+    {
+	let (term, multi) = block(4);
+	let mut bar = multi.add_bar(10);
+	let mut spin = multi.add_spin(Default::default());
+	bar.set_progress(0.5);
+	spin.set_title("loading");
+	assert_eq!(term.screen(), ["[=====     ]: 50.00%", "loading -", "", ""]);
+	assert_eq!(term.cursor(), (2, 0));
+
+	bar.set_progress(0.7);
+	spin.bump();
+	assert_eq!(term.screen(), ["[=======   ]: 70.00%", "loading \\", "", ""]);
+	assert_eq!(term.cursor(), (2, 0));
+
+	multi.blank();
+	assert_eq!(term.screen(), ["", "", "", ""]);
+	multi.refresh();
+	assert_eq!(term.screen(), ["[=======   ]: 70.00%", "loading \\", "", ""]);
+	assert!(term.scrollback().is_empty());
+    }
+
+    #[test]
+    fn adding_removing_rows()
+    {
+	let (term, multi) = block(5);
+	let mut first = multi.add_bar(10);
+	first.set_progress(0.5);
+	let mut second = multi.add_bar(10);
+	second.set_progress(0.2);
+	assert_eq!(multi.len(), 2);
+	assert_eq!(term.screen(), ["[=====     ]: 50.00%", "[==        ]: 20.00%", "", "", ""]);
+
+	let mut third = multi.add_spin(Default::default());
+	third.set_title("third");
+	assert_eq!(term.screen(), ["[=====     ]: 50.00%", "[==        ]: 20.00%", "third -", "", ""]);
+
+	first.set_progress(1.0);
+	first.complete().unwrap();
+	assert_eq!(multi.len(), 2);
+	assert_eq!(term.screen(), ["[==========]: 100.00%", "[==        ]: 20.00%", "third -", "", ""]);
+
+	drop(second.with_finish_mode(progress::FinishMode::Clear));
+	assert_eq!(multi.len(), 1);
+	assert_eq!(term.screen(), ["[==========]: 100.00%", "third -", "", "", ""]);
+
+	third.complete_with("done").unwrap();
+	assert!(multi.is_empty());
+	assert_eq!(term.screen(), ["[==========]: 100.00%", "third done", "", "", ""]);
+	assert_eq!(term.cursor(), (2, 0));
+    }
+
+    #[test]
+    fn printing_above()
+    {
+	let (term, multi) = block(6);
+	let mut bar = multi.add_bar(10);
+	bar.set_progress(0.5);
+	multi.println("first\nsecond");
+	assert_eq!(term.screen(), ["first", "second", "[=====     ]: 50.00%", "", "", ""]);
+
+	let spin = multi.add_spin(Default::default());
+	spin.println("from a row");
+	assert_eq!(term.screen(), ["first", "second", "from a row", "[=====     ]: 50.00%", " -", ""]);
+	assert!(term.scrollback().is_empty());
+	bar.println("scrolled");
+	assert_eq!(term.scrollback(), ["first"]);
+	assert_eq!(term.screen(), ["second", "from a row", "scrolled", "[=====     ]: 50.00%", " -", ""]);
+    }
+
+    #[test]
+    fn hiding_cursor()
+    {
+	let (term, multi) = block(5);
+	let mut bar = multi.add_bar(10).with_hide_cursor(true);
+	let mut spin = multi.add_spin(Default::default()).with_hide_cursor(true);
+	bar.set_progress(0.5);
+	assert!(!term.cursor_visible());
+	spin.bump();
+	bar.complete().unwrap();
+	assert!(!term.cursor_visible(), "the spinner is still hiding it");
+	drop(spin);
+	assert!(term.cursor_visible());
+
+	let mut bar = multi.add_bar(10).with_hide_cursor(true);
+	bar.set_progress(0.5);
+	assert!(!term.cursor_visible());
+	drop(bar);
+	assert!(term.cursor_visible(), "a dropped row stops hiding it");
+    }
+}
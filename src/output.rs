@@ -0,0 +1,144 @@
+//! Selecting the standard stream that indicators draw to when no output is user-specified.
+
+use std::{
+    io,
+    os::fd::{AsFd, BorrowedFd},
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+/// A standard stream that the `*_default` family of constructors can draw indicators to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[repr(u8)]
+pub enum OutputStream
+{
+    /// `stderr`. This is the default, so that indicators never mix with data written to `stdout`.
+    #[default]
+    Stderr = 0,
+    /// `stdout`.
+    Stdout = 1,
+}
+
+impl OutputStream
+{
+    /// Create a new output device handle for this stream.
+    ///
+    /// This can be passed to any constructor taking a `DefaultOutputDevice` to choose the stream for a single indicator.
+    #[inline]
+    pub fn device(self) -> DefaultOutputDevice
+    {
+	match self {
+	    Self::Stderr => DefaultOutputDevice::Stderr(io::stderr()),
+	    Self::Stdout => DefaultOutputDevice::Stdout(io::stdout()),
+	}
+    }
+
+    #[inline(always)]
+    const fn from_u8(from: u8) -> Self
+    {
+	match from {
+	    1 => Self::Stdout,
+	    _ => Self::Stderr,
+	}
+    }
+}
+
+static DEFAULT_OUTPUT_STREAM: AtomicU8 = AtomicU8::new(OutputStream::Stderr as u8);
+
+/// Set the stream that indicators created with the `*_default` family of constructors are drawn to.
+///
+/// This only affects indicators created after the call, existing indicators keep drawing to the stream they were created with.
+#[inline]
+pub fn set_default_output_stream(stream: OutputStream)
+{
+    DEFAULT_OUTPUT_STREAM.store(stream as u8, Ordering::Relaxed);
+}
+
+/// Get the stream that indicators created with the `*_default` family of constructors are drawn to.
+///
+/// Unless changed with `set_default_output_stream()`, this is `OutputStream::Stderr`.
+#[inline]
+pub fn default_output_stream() -> OutputStream
+{
+    OutputStream::from_u8(DEFAULT_OUTPUT_STREAM.load(Ordering::Relaxed))
+}
+
+/// The default place to write bars to if an output is not user-specified.
+///
+/// This is a handle to either `stdout` or `stderr`, see `OutputStream`.
+#[derive(Debug)]
+pub enum DefaultOutputDevice
+{
+    Stderr(io::Stderr),
+    Stdout(io::Stdout),
+}
+
+impl DefaultOutputDevice
+{
+    /// The stream this device writes to.
+    #[inline]
+    pub fn stream(&self) -> OutputStream
+    {
+	match self {
+	    Self::Stderr(_) => OutputStream::Stderr,
+	    Self::Stdout(_) => OutputStream::Stdout,
+	}
+    }
+}
+
+impl From<OutputStream> for DefaultOutputDevice
+{
+    #[inline]
+    fn from(from: OutputStream) -> Self
+    {
+	from.device()
+    }
+}
+
+impl Default for DefaultOutputDevice
+{
+    #[inline]
+    fn default() -> Self
+    {
+	default_output_stream().device()
+    }
+}
+
+impl io::Write for DefaultOutputDevice
+{
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize>
+    {
+	match self {
+	    Self::Stderr(e) => e.write(buf),
+	    Self::Stdout(o) => o.write(buf),
+	}
+    }
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()>
+    {
+	match self {
+	    Self::Stderr(e) => e.write_all(buf),
+	    Self::Stdout(o) => o.write_all(buf),
+	}
+    }
+    #[inline]
+    fn flush(&mut self) -> io::Result<()>
+    {
+	match self {
+	    Self::Stderr(e) => e.flush(),
+	    Self::Stdout(o) => o.flush(),
+	}
+    }
+}
+
+impl AsFd for DefaultOutputDevice
+{
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_>
+    {
+	match self {
+	    Self::Stderr(e) => e.as_fd(),
+	    Self::Stdout(o) => o.as_fd(),
+	}
+    }
+}
@@ -1,9 +1,21 @@
-///! Progress bar that has a size and also a max size.
+//! Progress bar that has a size and also a max size.
 
 use super::*;
+use error::ErrorState;
 use std::{
     fmt::Write,
     io,
+    sync::{
+	OnceLock,
+	atomic::{
+	    AtomicBool,
+	    Ordering,
+	},
+    },
+    time::{
+	Duration,
+	Instant,
+    },
 };
 /// A progress bar with a size and optionally title. It implements the `ProgressBar` trait, and is the default progress bar.
 ///
@@ -18,11 +30,35 @@ use std::{
 /// let mut bar = Bar::default(); //Creates a bar of width 50 by default.
 /// ```
 ///
+/// Bars created through `Default` or the `*_default` family of constructors draw to the default output stream, which is `stderr` unless changed with `set_default_output_stream()`.
+/// To choose the stream for a single bar, pass a device for it to a constructor:
+/// ```rust
+/// # use termprogress::{prelude::*, OutputStream};
+/// let mut bar: Bar = Bar::new(OutputStream::Stdout.device(), 50); //Always draws to stdout.
+/// ```
+///
+/// Any `io::Write` can be drawn to. Outputs that are not file descriptors (e.g. a `Vec<u8>`) are created with `with_size()`, which takes the size of the terminal from a `size::SizeQuery` instead.
+///
 /// You can configure sizes and initial title with `new()`, `with_title()`, and `with_max()` functions.
 /// # How it looks
 /// It renders in the terminal like:
 /// `[=========================                         ]: 50% this is a title that may get cut if it reaches max le...`
 ///
+/// With a length (see `set_length()`), and the rate, elapsed and remaining time enabled (see `with_rate()`, `with_elapsed()` and `with_eta()`), it renders like:
+/// `[=========================                         ]: 50.00% 1,234/2,468 19.0 it/s 01:05 ETA 01:05 this is a title`
+///
+/// The characters the bar itself is drawn with can be changed with `with_style()`, e.g. `style::BarStyle::SMOOTH` draws it with 8 steps per cell:
+/// `│████████████████████████▌                         │: 49.00% this is a title`
+///
+/// # Logs and CI
+/// If the output is not a terminal (or is a file descriptor and the `CI` environment variable is set), the bar instead prints its progress as plain lines like `title: 40%` at intervals, without any escape codes.
+/// See `lines::DrawMode` and `with_line_interval()`.
+///
+/// # Finishing
+/// What the bar leaves on its line when it is completed is decided by its `FinishMode` (see `with_finish_mode()`.) By default the last frame is left as it is.
+/// A bar that has been drawn is also finished this way when it is dropped without being completed.
+/// With `with_hide_cursor()`, the cursor is hidden from when the bar is first drawn until it is finished.
+///
 /// # Thread `Sync`safety
 /// This type is safely `Sync` (where `T` is), the behaviour is defined to prevent overlapping writes to `T`.
 /// Though it is *advised* to not render a `Bar` from more than a single thread, you still safely can.
@@ -37,10 +73,46 @@ pub struct Bar<T: ?Sized = DefaultOutputDevice>
     width: usize,
     max_width: usize,
     progress: f64,
+    position: u64,
+    length: Option<u64>,
+    estimator: estimate::Estimator,
+    throughput: estimate::Estimator,
+    unit: format::CountUnit,
+    show_elapsed: bool,
+    show_eta: bool,
+    show_rate: bool,
+    template: template::Template,
+    style: style::BarStyle,
+    colors: color::BarColors,
+    color_level: OnceLock<color::ColorLevel>,
+    draw_mode: OnceLock<lines::DrawMode>,
+    log: lines::LineLog,
+    draw_interval: Option<Duration>,
+    last_draw: Option<Instant>,
+    /// Has a redraw been skipped because of `draw_interval`?
+    undrawn: bool,
+    spinner: wheel::Wheel,
+    /// The index of the current frame of `spinner`.
+    spinner_frame: usize,
+    /// The number of columns every frame of `spinner` is drawn in.
+    spinner_width: usize,
     buffer: String,
+    /// The length in bytes of the filled part of `buffer`.
+    buffer_filled: usize,
     title: String,
-    #[cfg(feature="size")]
+    frame: AtomicRefCell<Frame>,
+    /// Has anything been written to the output?
+    drawn: AtomicBool,
+    finish_mode: FinishMode,
+    finished: bool,
+    /// Finishes the bar when it is dropped. `Drop` cannot require `T: io::Write` without the struct doing so, so the constructors (which can) store it here.
+    finish_on_drop: Option<fn(&mut Self)>,
+    /// Set if the output is a terminal, so the panic hook can blank the bar's line (see `cleanup`.)
+    registration: Option<cleanup::Registration>,
+    cursor: cleanup::Cursor,
     fit_to_term: bool,
+    size: Box<dyn size::SizeQuery>,
+    errors: ErrorState,
     
     // Allowing `Bar` to manage the sync will ensure that the bar is not interrupted by another bar-related write, and so any accidental inter-thread corrupting writes will not be drawn (unlike if we relied on `T`'s sync, since we have multiple `write()` calls when rendering and blanking.) *NOTE*: using `AtomicRefCell` i think is actually still be preferable for those reasons. If `T` can be shared and written to with internal sync (like stdout/err,) then non-`Bar` writes are not affected, but `Bar` writes are better contained.
     output: AtomicRefCell<T>
@@ -76,7 +148,7 @@ impl Default for Bar
 
 
 impl Bar {
-    /// Create a new bar `width` long with a title using the default output descriptor `stdout`.
+    /// Create a new bar `width` long with a title using the default output stream (see `default_output_stream()`).
     #[inline] 
     pub fn with_title_default(width: usize, title: impl AsRef<str>) -> Self
     {
@@ -85,7 +157,7 @@ impl Bar {
 
     /// Attempt to create a new bar with max display width of our terminal and a title.
     ///
-    /// If `stdout` is not a terminal, then `None` is returned.
+    /// If the default output stream is not a terminal, then `None` is returned.
     #[cfg(feature="size")]
     #[inline] 
     pub fn try_new_with_title_default(width: usize, title: impl AsRef<str>) -> Option<Self>
@@ -107,7 +179,7 @@ impl Bar {
     
     /// Attempt to create a new bar with max display width of our terminal.
     ///
-    /// If `stdout` is not a terminal, then `None` is returned.
+    /// If the default output stream is not a terminal, then `None` is returned.
     #[cfg(feature="size")]
     #[inline] 
     pub fn try_new_default(width: usize) -> Option<Self>
@@ -117,7 +189,7 @@ impl Bar {
     
     /// Attempt to create a new bar with max display width of our terminal.
     ///
-    /// If `stdout` is not a terminal, then `None` is returned.
+    /// If the default output stream is not a terminal, then `None` is returned.
     #[cfg(feature="size")]
     #[inline] 
     pub fn try_new_default_size_default() -> Option<Self>
@@ -146,60 +218,32 @@ impl<T: io::Write + AsFd> Bar<T>
 	this
     }
 
-    #[inline(always)] fn add_title(&mut self, title: &str)
-    {
-	self.set_title(title);
-	self.update()
-    }
-    
     /// Attempt to create a new bar with max display width of our terminal and a title.
     ///
     /// If `output` is not a terminal, then `None` is returned.
     #[cfg(feature="size")]
     pub fn try_new_with_title(output: impl Into<T> + AsFd, width: usize, title: impl AsRef<str>) -> Option<Self>
     {
-	let (terminal_size::Width(tw), _) = terminal_size_of(&output)?;
-	let tw = usize::from(tw);
-	let mut o = Self::with_max(output.into(), if width < tw {width} else {tw}, tw);
+	let mut o = Self::try_new(output, width)?;
 	o.set_title(title.as_ref());
-	o.fit_to_term = true;
 	o.update();
 	Some(o)
     }
 
-    #[inline]
-    fn autofit(&mut self)
-    {
-	#[cfg(feature="size")]
-	self.fit();
-    }
-    
     /// Create a new bar with max display width of our terminal
     ///
     /// # Notes
     /// Without feature `size`, will be the same as `Self::with_max(width, width +20)`
     ///
     /// To try to create one that always adheres to `size`, use the `try_new()` family of functions.
-    #[cfg_attr(not(feature="size"), inline)]
+    #[inline]
     pub fn new(output: impl Into<T> + AsFd, width: usize) -> Self
     {
-	#[cfg(feature="size")]
-	return {
-	    if let Some((terminal_size::Width(tw), _)) = terminal_size_of(&output) {
-		let tw = usize::from(tw);
-		let mut o = Self::with_max(output.into(), if width < tw {width} else {tw}, tw);
-		o.fit_to_term = true;
-		o
-	    } else {
-		let mut o = Self::with_max(output.into(), width, width + DEFAULT_MAX_BORDER_SIZE);
-		o.fit_to_term = true;
-		o
-	    }
-	};
-	#[cfg(not(feature="size"))]
-	return {
-	    Self::with_max(output.into(), width, width +DEFAULT_MAX_BORDER_SIZE)
-	};
+	let size = size::FdSize::boxed(&output);
+	let (registration, stream) = (cleanup::Registration::new(&output), cleanup::StreamId::of(&output));
+	let mut this = Self::sized(output.into(), width, size);
+	this.register(registration, stream);
+	this
     }
 
     /// Attempt to create a new bar with max display width of our terminal.
@@ -208,10 +252,12 @@ impl<T: io::Write + AsFd> Bar<T>
     #[cfg(feature="size")]
     pub fn try_new(output: impl Into<T> + AsFd, width: usize) -> Option<Self>
     {
-	let (terminal_size::Width(tw), _) = terminal_size_of(&output)?;
-	let tw = usize::from(tw);
-	let mut o = Self::with_max(output.into(), if width < tw {width} else {tw}, tw);
+	let size = size::FdSize::boxed(&output);
+	let tw = size.width()?;
+	let (registration, stream) = (cleanup::Registration::new(&output), cleanup::StreamId::of(&output));
+	let mut o = Self::with_max_sized(output.into(), if width < tw {width} else {tw}, tw, size);
 	o.fit_to_term = true;
+	o.register(registration, stream);
 	Some(o)
     }
     
@@ -229,305 +275,1482 @@ impl<T: io::Write + AsFd> Bar<T>
     ///
     /// # Panics
     /// If `width` is larger than or equal to `max_width`.
+    #[inline]
     pub fn with_max(output: impl Into<T>, width: usize, max_width: usize) -> Self
+    {
+	let output = output.into();
+	let size = size::FdSize::boxed(&output);
+	let (registration, stream) = (cleanup::Registration::new(&output), cleanup::StreamId::of(&output));
+	let mut this = Self::with_max_sized(output, width, max_width, size);
+	this.register(registration, stream);
+	this
+    }
+}
+
+impl<T: io::Write> Bar<T>
+{
+    /// Create a new bar `width` long drawing to any `output`, where the size of the terminal (if there is one) is queried from `size`.
+    ///
+    /// This is how to draw to a writer that is not backed by a file descriptor, e.g. a `Vec<u8>` or a `BufWriter`:
+    /// ```rust
+    /// # use termprogress::{prelude::*, size::FixedSize};
+    /// let mut bar: Bar<Vec<u8>> = Bar::with_size(Vec::new(), 20, FixedSize(80));
+    /// bar.set_progress(0.5);
+    /// ```
+    /// See the `size` module.
+    #[inline]
+    pub fn with_size(output: impl Into<T>, width: usize, size: impl size::SizeQuery + 'static) -> Self
+    {
+	Self::sized(output.into(), width, Box::new(size))
+    }
+
+    #[inline(always)] fn add_title(&mut self, title: &str)
+    {
+	self.set_title(title);
+	self.update()
+    }
+
+    /// Create a new bar `width` long, with a max display width of the terminal queried from `size` (or `width + 20` if it cannot be.)
+    fn sized(output: T, width: usize, size: Box<dyn size::SizeQuery>) -> Self
+    {
+	let mut o = match size.width() {
+	    Some(tw) => Self::with_max_sized(output, if width < tw {width} else {tw}, tw, size),
+	    None => Self::with_max_sized(output, width, width + DEFAULT_MAX_BORDER_SIZE, size),
+	};
+	o.fit_to_term = true;
+	o
+    }
+
+    /// Create a bar with a max display width, where the size of the terminal is queried from `size`.
+    fn with_max_sized(output: T, width: usize, max_width: usize, size: Box<dyn size::SizeQuery>) -> Self
     {
 	let mut this = Self {
 	    width,
 	    max_width,
 	    progress: 0.0,
+	    position: 0,
+	    length: None,
+	    estimator: estimate::Estimator::new(Instant::now()),
+	    throughput: estimate::Estimator::new(Instant::now()),
+	    unit: Default::default(),
+	    show_elapsed: false,
+	    show_eta: false,
+	    show_rate: false,
+	    template: Default::default(),
+	    style: Default::default(),
+	    colors: Default::default(),
+	    color_level: OnceLock::new(),
+	    draw_mode: OnceLock::new(),
+	    log: Default::default(),
+	    draw_interval: None,
+	    last_draw: None,
+	    undrawn: false,
+	    spinner: Default::default(),
+	    spinner_frame: 0,
+	    spinner_width: 1,
 	    buffer: String::with_capacity(width),
+	    buffer_filled: 0,
 	    title: String::with_capacity(max_width - width),
-	    #[cfg(feature="size")] 
+	    frame: Default::default(),
+	    drawn: AtomicBool::new(false),
+	    finish_mode: Default::default(),
+	    finished: false,
+	    finish_on_drop: Some(Self::finish_on_drop),
+	    registration: None,
+	    cursor: Default::default(),
 	    fit_to_term: false,
-	    output: AtomicRefCell::new(output.into())
+	    size,
+	    errors: Default::default(),
+	    output: AtomicRefCell::new(output)
 	};
 	this.update();
 	this
     }
-
 }
 
-impl<T: ?Sized + io::Write + AsFd> Bar<T> {
-    #[inline(always)]
-    #[cfg(feature="size")]
-    fn try_get_size(&self) -> Option<(terminal_size::Width, terminal_size::Height)>
+impl<T: ?Sized + io::Write> Bar<T> {
+    /// Get the width of the terminal we are drawing to.
+    ///
+    /// With feature `reactive`, this is cached until the terminal is resized.
+    #[inline]
+    fn terminal_width(&self) -> Option<usize>
     {
-	let b = self.output.try_borrow().ok()?;
-	terminal_size::terminal_size_of::<&T>(&b)
+	self.size.width()
     }
+
     /// Fit to terminal's width if possible.
     ///
     /// # Notes
-    /// Available with feature `size` only.
+    /// Only succeeds if the bar's `size::SizeQuery` knows the width of the terminal (which, for one drawing to a file descriptor, requires feature `size`.)
     ///
     /// # Returns
     /// If the re-fit succeeded.
-    /// A `fit()` will also fail if another thread is already engaging in a display operation.
     pub fn fit(&mut self) -> bool
     {
-	#[cfg(feature="size")] {
-	    if let Some((terminal_size::Width(tw), _)) = terminal_size::terminal_size_of(self.output.get_mut()) {
-		let tw = usize::from(tw);
-		self.width = if self.width < tw {self.width} else {tw};
-		self.update_dimensions(tw);
-		return true;
-	    }
+	self.size.invalidate();
+	if let Some(tw) = self.terminal_width() {
+	    self.width = if self.width < tw {self.width} else {tw};
+	    self.update_dimensions(tw);
+	    return true;
 	}
 	false
     }
 
     #[inline] fn widths(&self) -> (usize, usize)
     {
-	#[cfg(feature="size")] 
-	if self.fit_to_term {
-	    if let Some((terminal_size::Width(tw), _)) = self.try_get_size() {
-		let tw = usize::from(tw);
-		let width = if self.width < tw {self.width} else {tw};
-		return (width, tw);
-	    }
-	};
+	if self.fit_to_term && let Some(tw) = self.terminal_width() {
+	    let width = if self.width < tw {self.width} else {tw};
+	    return (width, tw);
+	}
 	(self.width, self.max_width)
     }
-    
-    /// Update the buffer.
-    pub fn update(&mut self)
+}
+
+impl<T: io::Write> Bar<T> {
+    /// Consume the bar and complete it, regardless of progress.
+    ///
+    /// The line is finished according to the bar's `FinishMode`. In `DrawMode::Lines`, the final progress is printed if it has not been already.
+    pub fn complete(mut self) -> io::Result<()>
     {
-	self.buffer.clear();
+	self.finish()
+    }
 
-	let pct = (self.progress * (self.width as f64)) as usize;
-	for i in 0..self.width
-	{
-	    if i >= pct {
-		write!(self.buffer, " ").unwrap();
-	    } else {
-		write!(self.buffer, "=").unwrap();
-	    }
+    /// Consume the bar and complete it with `mode`, instead of its own `FinishMode`.
+    #[inline]
+    pub fn complete_with(mut self, mode: FinishMode) -> io::Result<()>
+    {
+	self.finish_mode = mode;
+	self.finish()
+    }
+}
+
+impl<T: ?Sized + io::Write> Bar<T> {
+    /// Finish the bar's line according to its `FinishMode`, unless it has already been finished.
+    fn finish(&mut self) -> io::Result<()>
+    {
+	if std::mem::replace(&mut self.finished, true) || self.errors.is_disabled() {
+	    return Ok(());
+	}
+	// The line is about to be finished, so there is nothing left for the panic hook to blank.
+	self.registration = None;
+	match self.finish_mode {
+	    FinishMode::Fill => {
+		if let Some(length) = self.length {
+		    self.position = length;
+		}
+		self.progress = 1.0;
+		self.update();
+	    },
+	    FinishMode::Abandon => self.title.insert_str(0, ABANDONED_MARKER),
+	    _ => (),
 	}
+	self.draw_with(Self::compose_finish)
     }
 
-}
-impl<T: io::Write> Bar<T> {
-    /// Consume the bar and complete it, regardless of progress.
-    pub fn complete(self) -> io::Result<()>
+    /// `finish()` the bar when it is dropped, if anything has been drawn and the panic hook has not already blanked it.
+    fn finish_on_drop(&mut self)
     {
-	writeln!(&mut self.output.into_inner(), "")
+	let cleaned = self.registration.as_ref().is_some_and(cleanup::Registration::is_cleaned);
+	if self.drawn.load(Ordering::Relaxed) && !cleaned {
+	    let res = self.finish();
+	    self.errors.handle(res);
+	}
     }
 }
 
-fn ensure_eq(input: String, to: usize) -> String
+impl<T: ?Sized> Drop for Bar<T>
 {
-    let  chars = input.chars();
-    if chars.count() != to {
-	let mut chars = input.chars();
-	let mut output = String::with_capacity(to);
-	for _ in 0..to
-	{
-	    if let Some(c) = chars.next() {
-		write!(output, "{}", c).unwrap();
-	    } else {
-		write!(output, " ").unwrap();
-	    }
+    fn drop(&mut self)
+    {
+	if let Some(finish) = self.finish_on_drop.take() {
+	    finish(self);
 	}
-	output
-    } else {
-	input
     }
 }
 
-
-fn ensure_lower(input: String, to: usize) -> String
+/// What a `Bar` leaves on its line when it is completed, or dropped without being completed.
+///
+/// It is also what a `spinner::Spin` leaves on its line when it is dropped without being completed, see `Spin::with_finish_mode()`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub enum FinishMode
 {
-    let chars = input.chars();
-    if chars.count() > to
+    /// Leave the bar as it was last drawn. A spinner is left with its frame removed.
+    #[default]
+    Leave,
+    /// Fill the bar to 100% and leave it. A spinner treats this as `Leave`.
+    Fill,
+    /// Clear the bar's line, leaving nothing behind.
+    Clear,
+    /// Replace the bar's line with a message, e.g. a summary of the work done.
+    Replace(String),
+    /// Leave the bar at its current progress, with its title marked `(abandoned)`.
+    Abandon,
+}
+
+/// What the title of an abandoned bar is prefixed with.
+pub(crate) const ABANDONED_MARKER: &str = "(abandoned) ";
+
+impl<T: ?Sized> Bar<T> {
+    /// The current position, see `set_length()`.
+    #[inline]
+    pub fn position(&self) -> u64
     {
-	let chars = input.chars();
-	let mut output = String::with_capacity(to);
-	for (i,c) in (0..).zip(chars)
-	{
-	    write!(output, "{}", c).unwrap();
-	    if to>3 && i == to-3 {
-		write!(output, "...").unwrap();
-		break;
-	    } else if i==to {
-		break;
-	    }
-	}
+	self.position
+    }
 
-	output
-    } else {
-	input
+    /// The length the position counts up to, if the bar has one.
+    ///
+    /// See `set_length()`.
+    #[inline]
+    pub fn length(&self) -> Option<u64>
+    {
+	self.length
     }
-}
 
-impl<T: ?Sized + io::Write + AsFd> Display for Bar<T>
-{
-    fn refresh(&self)
+    /// Set whether the time elapsed since the bar was created is rendered after the percentage.
+    ///
+    /// The change is rendered on the next display operation.
+    #[inline]
+    pub fn set_show_elapsed(&mut self, show: bool)
     {
-	let (_, max_width) = self.widths();
-	
-	let temp = format!("[{}]: {:.2}%", self.buffer, self.progress * 100.00);
-	let title = ensure_lower(format!(" {}", self.title), max_width - temp.chars().count());
+	self.show_elapsed = show;
+    }
 
-	let temp = ensure_eq(format!("{}{}", temp, title), max_width);
-	
-	// If another thread is writing, just abort (XXX: Is this the best way to handle it?)
-	//
-	// We acquire the lock after work allocation and computation to keep it for the shortest amount of time, this is an acceptible tradeoff since multiple threads shouldn't be calling this at once anyway.
-	let Ok(mut out) = self.output.try_borrow_mut() else { return };
-	
-	//TODO: What to do about I/O errors?
-	let _ = write!(out, "\x1B[0m\x1B[K{}", temp) // XXX: For now, just abort if one fails.
-	    .and_then(|_| write!(out, "\n\x1B[1A"))
-	    .and_then(move |_| flush!(? out)); 
+    /// Set whether the estimated time remaining is rendered after the percentage.
+    ///
+    /// The change is rendered on the next display operation.
+    #[inline]
+    pub fn set_show_eta(&mut self, show: bool)
+    {
+	self.show_eta = show;
     }
 
-    fn blank(&self)
+    /// Lay out the bar's line with `template`.
+    ///
+    /// The change is rendered on the next display operation.
+    #[inline]
+    pub fn set_template(&mut self, template: template::Template)
     {
-	let (_, max_width) = self.widths();
+	self.template = template;
+    }
 
-	// If another thread is writing, just abort (XXX: Is this the best way to handle it?)
-	let Ok(mut out) = self.output.try_borrow_mut() else { return };
-	
-	//TODO: What to do about I/O errors?
-	let _ = out.write_all(b"\r")
-	    .and_then(|_| stackalloc::stackalloc(max_width, b' ',|spaces| out.write_all(spaces))) // Write `max_width` spaces (TODO: Is there a better way to do this? With no allocation? With a repeating iterator maybe?)
-	    .and_then(|_| out.write_all(b"\r"))
-	    .and_then(move |_| flush!(? out));
+    /// The template the bar's line is laid out with.
+    #[inline]
+    pub fn template(&self) -> &template::Template
+    {
+	&self.template
     }
 
-    fn get_title(&self) -> &str
+    /// Draw the bar itself with `style`.
+    ///
+    /// The change is rendered on the next display operation.
+    #[inline]
+    pub fn set_style(&mut self, style: style::BarStyle)
     {
-	&self.title
+	self.style = style;
+	self.update();
     }
 
-    fn set_title(&mut self, from: &str)
+    /// The style the bar itself is drawn with.
+    #[inline]
+    pub fn style(&self) -> &style::BarStyle
     {
-	self.title = from.to_string();
+	&self.style
+    }
 
-	let (_, max_width) = self.widths();
+    /// Set what the bar leaves on its line when it is completed or dropped.
+    #[inline]
+    pub fn set_finish_mode(&mut self, mode: FinishMode)
+    {
+	self.finish_mode = mode;
+    }
 
-	// self.refresh(), with exclusive access. (XXX: Maybe move this to a non-pub `&mut self` helper function)
-	let out = self.output.get_mut();
-	
-	//TODO: What to do about I/O errors?
-	let _ = out.write_all(b"\r")
-	    .and_then(|_| stackalloc::stackalloc(max_width, b' ',|spaces| out.write_all(spaces))) // Write `max_width` spaces (TODO: Is there a better way to do this? With no allocation? With a repeating iterator maybe?)
-	    .and_then(|_| out.write_all(b"\r"))
-	    .and_then(move |_| flush!(? out));
+    /// What the bar leaves on its line when it is completed or dropped.
+    #[inline]
+    pub fn finish_mode(&self) -> &FinishMode
+    {
+	&self.finish_mode
     }
 
-    fn update_dimensions(&mut self, to: usize)
+    /// Hide the cursor from when the bar is first drawn until it is completed or dropped.
+    ///
+    /// See `cleanup` for how bars and spinners drawing to the same terminal share it.
+    #[inline]
+    pub fn set_hide_cursor(&mut self, hide: bool)
     {
-	self.max_width = to;
-	
-	// self.refresh(), with exclusive access. (XXX: Maybe move this to a non-pub `&mut self` helper function)
-	let out = self.output.get_mut();
-	
-	//TODO: What to do about I/O errors?
-	let _ = out.write_all(b"\r")
-	    .and_then(|_| stackalloc::stackalloc(to, b' ',|spaces| out.write_all(spaces))) // Write `max_width` spaces (TODO: Is there a better way to do this? With no allocation? With a repeating iterator maybe?)
-	    .and_then(|_| out.write_all(b"\r"))
-	    .and_then(move |_| flush!(? out));
+	self.cursor.set_hide(hide);
     }
-}
 
-impl<T: ?Sized + io::Write + AsFd> ProgressBar for Bar<T>
-{
-    fn get_progress(&self) -> f64
+    /// Does the bar hide the cursor while it is drawn?
+    #[inline]
+    pub fn hide_cursor(&self) -> bool
     {
-	self.progress
+	self.cursor.hides()
     }
-    fn set_progress(&mut self, value: f64)
+
+    /// Let the panic hook blank the bar's terminal, and share the cursor of `stream` with the other indicators drawing to it.
+    fn register(&mut self, registration: Option<cleanup::Registration>, stream: Option<cleanup::StreamId>)
     {
-	if self.progress != value {
-	    self.progress = value;
-	    self.update();
-	}
-	
-	let (_, max_width) = self.widths();
+	self.registration = registration;
+	self.cursor.set_stream(stream);
+    }
 
-	// self.refresh(), with exclusive access. (XXX: Maybe move this to a non-pub `&mut self` helper function)
-	let out = self.output.get_mut();
-	
-	//TODO: What to do about I/O errors?
-	let _ = out.write_all(b"\r")
-	    .and_then(|_| stackalloc::stackalloc(max_width, b' ',|spaces| out.write_all(spaces))) // Write `max_width` spaces (TODO: Is there a better way to do this? With no allocation? With a repeating iterator maybe?)
-	    .and_then(|_| out.write_all(b"\r"))
-	    .and_then(move |_| flush!(? out));
+    /// Draw the `{spinner}` of the template with the frames of `wheel`.
+    ///
+    /// The change is rendered on the next display operation.
+    #[inline]
+    pub fn set_spinner(&mut self, wheel: wheel::Wheel)
+    {
+	self.spinner_width = wheel.width();
+	self.spinner = wheel;
     }
-}
 
-impl<T: io::Write + AsFd> WithTitle for Bar<T>
-{
-    fn add_title(&mut self, string: impl AsRef<str>)
+    /// Colour the parts of the bar's line with `colors`.
+    ///
+    /// The change is rendered on the next display operation.
+    #[inline]
+    pub fn set_colors(&mut self, colors: color::BarColors)
     {
-	(*self).add_title(string.as_ref())
+	self.colors = colors;
     }
-    fn update(&mut self)
+
+    /// The colours of the parts of the bar's line.
+    #[inline]
+    pub fn colors(&self) -> &color::BarColors
     {
-	self.update();
+	&self.colors
     }
-    fn complete(self)
+
+    /// Render colours at `level`, instead of detecting it from the environment and output.
+    ///
+    /// The change is rendered on the next display operation.
+    #[inline]
+    pub fn set_color_level(&mut self, level: color::ColorLevel)
     {
-	//TODO: What to do about I/O errors?
-	let _ = self.complete();
+	self.color_level = OnceLock::from(level);
     }
-}
 
-const _:() = {
-    const fn declval<T>() -> Bar<T> {
-	unreachable!()
+    /// Draw the bar in `mode`, see `lines::DrawMode`.
+    ///
+    /// `DrawMode::Auto` is decided again the next time the bar is drawn.
+    #[inline]
+    pub fn set_draw_mode(&mut self, mode: lines::DrawMode)
+    {
+	self.draw_mode = match mode {
+	    lines::DrawMode::Auto => OnceLock::new(),
+	    mode => OnceLock::from(mode),
+	};
     }
-    fn take_title(_: &(impl WithTitle + ?Sized)) {}
-    fn take_progress(_: &(impl ProgressBar + ?Sized)) {}
-    fn take_display(_: &(impl Display + ?Sized)) {}
-    fn test()
+
+    /// Set how often progress is printed in `DrawMode::Lines`.
+    #[inline]
+    pub fn set_line_interval(&mut self, interval: lines::Interval)
     {
-	macro_rules! assert_is_bar {
-	    ($ty:path) => {
-		take_title(&declval::<$ty>());
-		take_progress(&declval::<$ty>());
-		take_display(&declval::<$ty>());
+	self.log.interval = interval;
+    }
+
+    /// Redraw at most once every `interval`, or on every change if `None`.
+    ///
+    /// See `with_draw_interval()`.
+    #[inline]
+    pub fn set_draw_interval(&mut self, interval: Option<Duration>)
+    {
+	self.draw_interval = interval;
+    }
+
+    /// The shortest time between redraws, if there is one.
+    #[inline]
+    pub fn draw_interval(&self) -> Option<Duration>
+    {
+	self.draw_interval
+    }
+
+    /// Set whether the rate the position is advancing at is rendered after the percentage.
+    ///
+    /// The change is rendered on the next display operation.
+    #[inline]
+    pub fn set_show_rate(&mut self, show: bool)
+    {
+	self.show_rate = show;
+    }
+
+    /// Set what the position and length count, which decides how they and the rate are formatted.
+    ///
+    /// The change is rendered on the next display operation.
+    #[inline]
+    pub fn set_unit(&mut self, unit: format::CountUnit)
+    {
+	self.unit = unit;
+    }
+
+    /// The smoothed rate the position is advancing at, in units per second.
+    ///
+    /// `None` until the position has changed at least once.
+    #[inline]
+    pub fn rate(&self) -> Option<f64>
+    {
+	self.throughput.rate()
+    }
+
+    /// The time elapsed since the bar was created.
+    #[inline]
+    pub fn elapsed(&self) -> std::time::Duration
+    {
+	self.estimator.elapsed(Instant::now())
+    }
+
+    /// The estimated time remaining, if there is an estimate yet.
+    #[inline]
+    pub fn eta(&self) -> Option<std::time::Duration>
+    {
+	self.estimator.eta()
+    }
+
+    /// Update the buffer.
+    pub fn update(&mut self)
+    {
+	self.buffer.clear();
+	self.buffer_filled = self.style.render_into(&mut self.buffer, self.width, self.progress);
+    }
+
+    /// Write the segments enabled with `with_rate()`, `with_elapsed()` and `with_eta()`, and the position and length if there is a length.
+    fn render_stats(&self, out: &mut String) -> std::fmt::Result
+    {
+	if let Some(length) = self.length {
+	    write!(out, " {}/{}", self.unit.count(self.position), self.unit.count(length))?;
+	}
+	if self.show_rate {
+	    write!(out, " {}", self.unit.rate(self.rate().unwrap_or(0.0)))?;
+	}
+	if self.show_elapsed {
+	    write!(out, " {}", format::HumanDuration(self.elapsed()))?;
+	}
+	if self.show_eta {
+	    match self.eta() {
+		Some(eta) => write!(out, " ETA {}", format::HumanDuration(eta))?,
+		None => out.push_str(" ETA --:--"),
 	    }
 	}
+	Ok(())
+    }
 
-	assert_is_bar!(io::Stdout);
-	assert_is_bar!(std::fs::File);
+    /// Compose the line printed in `DrawMode::Lines`, e.g. `title: 40%`.
+    fn compose_log(&self, frame: &mut Frame)
+    {
+	let out = &mut frame.out;
+	if !self.title.is_empty() {
+	    out.push_str(&self.title);
+	    out.push_str(": ");
+	}
+	// Writing to a `String` cannot fail.
+	let _ = write!(out, "{:.0}%", self.progress * 100.0);
+	let _ = self.render_stats(out);
+	out.push('\n');
     }
-};
 
-#[cfg(test)]
-mod test
-{
-    use super::*;
-    
-    #[test]
-    fn rendering_blanking()
+    /// Set both the position and the length, without redisplaying.
+    pub(crate) fn sync_counts(&mut self, position: u64, length: u64)
     {
-	let mut bar = {
-	    #[cfg(feature="size")] 
-	    let Some(bar)= Bar::try_new_default_size_default() else { return };
-	    #[cfg(not(feature="size"))] 
-	    let bar= Bar::new_default(50);
-	    bar
-	};
-	bar.set_progress(0.5);
-	bar.blank();
-	bar.set_progress(0.7);
-	bar.set_title("70 percent.");
-	bar.refresh();
-	//println!();
-	bar.set_progress(0.2);
-	bar.set_title("...");
-	bar.blank();
-	bar.complete().unwrap();
+	self.position = position;
+	self.length = Some(length);
+	self.sync_progress();
     }
 
-    #[test]
-    fn creating_non_default_fd() {
-	#[cfg(feature="size")] 
-	let Some(_): Option<Bar<std::io::Stderr>> = Bar::try_new(std::io::stderr(), super::DEFAULT_SIZE) else { return };
+    /// Recompute the progress from the position and length, if there is a length.
+    fn sync_progress(&mut self)
+    {
+	self.throughput.record(self.position as f64, Instant::now());
+	if let Some(length) = self.length {
+	    self.progress = if length == 0 {
+		1.0
+	    } else {
+		(self.position as f64 / length as f64).min(1.0)
+	    };
+	    self.estimator.record(self.progress, Instant::now());
+	    self.update();
+	}
+    }
+
+    /// Set the policy for handling I/O errors that occur during infallible display operations.
+    ///
+    /// See `ErrorPolicy`.
+    #[inline]
+    pub fn set_error_policy(&mut self, policy: ErrorPolicy)
+    {
+	self.errors.set_policy(policy);
+    }
+
+    /// Take the most recent I/O error that occured during a display operation.
+    ///
+    /// Errors are only remembered with `ErrorPolicy::Remember`.
+    #[inline]
+    pub fn take_error(&self) -> Option<io::Error>
+    {
+	self.errors.take()
+    }
+
+    /// Has rendering been disabled by the error policy?
+    ///
+    /// When disabled, all display operations (including the `try_*` family) do nothing and succeed.
+    #[inline]
+    pub fn is_disabled(&self) -> bool
+    {
+	self.errors.is_disabled()
+    }
+}
+
+impl<T> Bar<T> {
+    /// Count progress as an integer position out of `length`, see `set_length()`.
+    #[inline]
+    pub fn with_length(mut self, length: u64) -> Self
+    {
+	self.length = Some(length);
+	self.sync_progress();
+	self
+    }
+
+    /// Render the time elapsed since the bar was created after the percentage.
+    #[inline]
+    pub fn with_elapsed(mut self) -> Self
+    {
+	self.show_elapsed = true;
+	self
+    }
+
+    /// Render the estimated time remaining after the percentage.
+    ///
+    /// See `estimate::Estimator` for how it is estimated.
+    #[inline]
+    pub fn with_eta(mut self) -> Self
+    {
+	self.show_eta = true;
+	self
+    }
+
+    /// Lay out the bar's line with `template`.
+    ///
+    /// See the `template` module for the syntax.
+    #[inline]
+    pub fn with_template(mut self, template: template::Template) -> Self
+    {
+	self.template = template;
+	self
+    }
+
+    /// Draw the bar itself with `style`.
+    ///
+    /// See `style::BarStyle`.
+    #[inline]
+    pub fn with_style(mut self, style: style::BarStyle) -> Self
+    {
+	self.set_style(style);
+	self
+    }
+
+    /// Set what the bar leaves on its line when it is completed or dropped.
+    ///
+    /// See `FinishMode`.
+    #[inline]
+    pub fn with_finish_mode(mut self, mode: FinishMode) -> Self
+    {
+	self.finish_mode = mode;
+	self
+    }
+
+    /// Hide the cursor from when the bar is first drawn until it is completed or dropped.
+    #[inline]
+    pub fn with_hide_cursor(mut self, hide: bool) -> Self
+    {
+	self.set_hide_cursor(hide);
+	self
+    }
+
+    /// Draw the `{spinner}` of the template with the frames of `wheel`.
+    ///
+    /// See `wheel::Wheel`.
+    #[inline]
+    pub fn with_spinner(mut self, wheel: wheel::Wheel) -> Self
+    {
+	self.set_spinner(wheel);
+	self
+    }
+
+    /// Colour the parts of the bar's line with `colors`.
+    ///
+    /// Colours are only rendered if the output supports them, see `color::ColorLevel::detect()`.
+    #[inline]
+    pub fn with_colors(mut self, colors: color::BarColors) -> Self
+    {
+	self.colors = colors;
+	self
+    }
+
+    /// Render colours at `level`, instead of detecting it from the environment and output.
+    #[inline]
+    pub fn with_color_level(mut self, level: color::ColorLevel) -> Self
+    {
+	self.set_color_level(level);
+	self
+    }
+
+    /// Draw the bar in `mode`.
+    ///
+    /// By default, bars whose output is not a terminal (or that draw to a file descriptor under CI) print their progress as plain lines instead of redrawing the line in place, see `lines::DrawMode`.
+    #[inline]
+    pub fn with_draw_mode(mut self, mode: lines::DrawMode) -> Self
+    {
+	self.set_draw_mode(mode);
+	self
+    }
+
+    /// Set how often progress is printed in `DrawMode::Lines`.
+    ///
+    /// See `lines::Interval`.
+    #[inline]
+    pub fn with_line_interval(mut self, interval: lines::Interval) -> Self
+    {
+	self.log.interval = interval;
+	self
+    }
+
+    /// Redraw at most once every `interval`.
+    ///
+    /// Changes made in between (e.g. by `set_position()` in a tight loop) only update the bar's state, and are drawn by the next change after `interval` has passed.
+    /// Completing the progress (and `complete()`) always draws, so the final state is never lost. `tick()` draws regardless of the interval.
+    ///
+    /// This has no effect in `DrawMode::Lines`, see `with_line_interval()` instead.
+    #[inline]
+    pub fn with_draw_interval(mut self, interval: Duration) -> Self
+    {
+	self.draw_interval = Some(interval);
+	self
+    }
+
+    /// Render the rate the position is advancing at after the percentage, e.g. `450 it/s`.
+    ///
+    /// See `with_unit()` to count bytes instead.
+    #[inline]
+    pub fn with_rate(mut self) -> Self
+    {
+	self.show_rate = true;
+	self
+    }
+
+    /// Set what the position and length count, which decides how they and the rate are formatted.
+    ///
+    /// See `format::CountUnit`.
+    #[inline]
+    pub fn with_unit(mut self, unit: format::CountUnit) -> Self
+    {
+	self.unit = unit;
+	self
+    }
+
+    /// Set the policy for handling I/O errors that occur during infallible display operations.
+    #[inline]
+    pub fn with_error_policy(mut self, policy: ErrorPolicy) -> Self
+    {
+	self.set_error_policy(policy);
+	self
+    }
+}
+
+/// Write `value` into `out`, with `precision` if one is given.
+fn write_with_precision(out: &mut String, value: impl std::fmt::Display, precision: Option<usize>) -> std::fmt::Result
+{
+    match precision {
+	Some(precision) => write!(out, "{:.*}", precision, value),
+	None => write!(out, "{}", value),
+    }
+}
+
+/// The buffers a frame is composed in.
+///
+/// They are kept between frames, so once they have grown to fit a frame, drawing does not allocate.
+#[derive(Debug, Default)]
+struct Frame
+{
+    /// The whole frame, written to the output at once.
+    out: String,
+    /// The line, before it is fitted to the width.
+    line: String,
+    scratch: template::Scratch,
+}
+
+impl Frame
+{
+    /// Compose blanking `width` columns of the current line.
+    fn blank(&mut self, width: usize)
+    {
+	self.out.push('\r');
+	self.out.extend(std::iter::repeat_n(' ', width));
+	self.out.push('\r');
+    }
+}
+
+/// Write a whole `frame` to `out` and flush it.
+///
+/// The frame is written with a single `write_all()`, so a slow terminal never shows a partly drawn line.
+fn write_frame<W: ?Sized + io::Write>(out: &mut W, frame: &str) -> io::Result<()>
+{
+    out.write_all(frame.as_bytes())
+	.and_then(move |_| flush!(? out))
+}
+
+impl<T: ?Sized + io::Write> Bar<T>
+{
+    /// The level colours are rendered at.
+    ///
+    /// Unless set with `set_color_level()`, it is detected from the environment and output the first time the bar is rendered (see `color::ColorLevel::detect()`.)
+    pub fn color_level(&self) -> color::ColorLevel
+    {
+	*self.color_level.get_or_init(|| color::ColorLevel::detect(self.size.is_terminal()))
+    }
+
+    /// The mode the bar is drawn in, never `DrawMode::Auto`.
+    ///
+    /// Unless set with `set_draw_mode()`, it is taken from the bar's `size::SizeQuery` the first time the bar is drawn (see `size::SizeQuery::draw_mode()`.)
+    pub fn draw_mode(&self) -> lines::DrawMode
+    {
+	*self.draw_mode.get_or_init(|| self.size.draw_mode())
+    }
+
+    /// Is the bar drawn as plain lines?
+    #[inline]
+    fn is_lines(&self) -> bool
+    {
+	self.draw_mode() == lines::DrawMode::Lines
+    }
+
+    /// Render the line into `out`, using `line` and `scratch` as buffers.
+    fn render_line(&self, out: &mut String, line: &mut String, scratch: &mut template::Scratch)
+    {
+	let (_, max_width) = self.widths();
+
+	line.clear();
+	let level = self.color_level();
+	self.template.render_into(line, scratch, max_width, &self.title, |placeholder, spec, out| self.render_value(placeholder, spec, out), |placeholder, text, out| {
+	    match placeholder {
+		template::Placeholder::Percent => self.colors.percent.paint(level, text, out),
+		template::Placeholder::Title => self.colors.title.paint(level, text, out),
+		_ => out.push_str(text),
+	    }
+	});
+	width::fit_into(out, line, max_width);
+    }
+
+    /// Compose drawing the line over the current line.
+    fn compose_line(&self, frame: &mut Frame)
+    {
+	let Frame { out, line, scratch } = frame;
+	if self.cursor.hide() {
+	    out.push_str(cleanup::HIDE_CURSOR);
+	}
+	out.push_str("\x1B[0m\x1B[K");
+	self.render_line(out, line, scratch);
+	out.push_str("\n\x1B[1A");
+    }
+
+    /// Compute the line to be rendered.
+    fn line(&self) -> String
+    {
+	let mut out = String::new();
+	self.render_line(&mut out, &mut String::new(), &mut Default::default());
+	out
+    }
+
+    /// Compose a frame with `compose` in the bar's reusable buffers, then write it to the output.
+    ///
+    /// If another thread is currently engaging in a display operation, nothing is written and `Ok(())` is returned.
+    fn draw_with(&self, compose: impl FnOnce(&Self, &mut Frame)) -> io::Result<()>
+    {
+	// If another thread is writing, just abort (XXX: Is this the best way to handle it?)
+	let Ok(mut frame) = self.frame.try_borrow_mut() else { return Ok(()) };
+	// Borrowed before composing, so a frame that hides or shows the cursor is never composed and then thrown away.
+	let Ok(mut out) = self.output.try_borrow_mut() else { return Ok(()) };
+	frame.out.clear();
+	compose(self, &mut frame);
+	self.drawn.store(true, Ordering::Relaxed);
+	if let Some(registration) = &self.registration {
+	    registration.activate();
+	}
+	write_frame(&mut *out, &frame.out)
+    }
+
+    /// Compose finishing the bar's line according to its `FinishMode`.
+    fn compose_finish(&self, frame: &mut Frame)
+    {
+	// A bar that was never drawn has not decided its mode yet, and has nothing on its line to finish.
+	let lines = matches!(self.draw_mode.get(), Some(lines::DrawMode::Lines));
+	match &self.finish_mode {
+	    FinishMode::Clear if lines => (),
+	    FinishMode::Clear => frame.blank(self.widths().1),
+	    FinishMode::Replace(msg) => {
+		if !lines {
+		    frame.blank(self.widths().1);
+		}
+		frame.out.push_str(msg);
+		frame.out.push('\n');
+	    },
+	    FinishMode::Abandon if lines => self.compose_log(frame),
+	    _ if lines => if self.log.due_final(self.progress) {
+		self.compose_log(frame);
+	    },
+	    mode => {
+		if self.undrawn || *mode != FinishMode::Leave {
+		    self.compose_line(frame);
+		}
+		frame.out.push('\n');
+	    },
+	}
+	if self.cursor.show() {
+	    frame.out.push_str(cleanup::SHOW_CURSOR);
+	}
+    }
+
+    /// Write the value of a template placeholder.
+    fn render_value(&self, placeholder: template::Placeholder, spec: &template::Spec, out: &mut String)
+    {
+	use template::Placeholder;
+	// Writing to a `String` cannot fail.
+	let _ = match placeholder {
+	    Placeholder::Bar | Placeholder::BracketedBar => {
+		let level = self.color_level();
+		let (filled, empty) = self.buffer.split_at(self.buffer_filled);
+		let bracketed = placeholder == Placeholder::BracketedBar;
+		if bracketed {
+		    out.extend(self.style.open);
+		}
+		self.colors.filled.paint(level, filled, out);
+		self.colors.empty.paint(level, empty, out);
+		if bracketed {
+		    out.extend(self.style.close);
+		}
+		Ok(())
+	    },
+	    Placeholder::Percent => write!(out, "{:.*}", spec.precision.unwrap_or(2), self.progress * 100.00),
+	    Placeholder::Title => out.write_str(&self.title),
+	    Placeholder::Position => write_with_precision(out, self.unit.count(self.position), spec.precision),
+	    Placeholder::Length => match self.length {
+		Some(length) => write_with_precision(out, self.unit.count(length), spec.precision),
+		None => out.write_char('?'),
+	    },
+	    Placeholder::Rate => write!(out, "{}", self.unit.rate(self.rate().unwrap_or(0.0))),
+	    Placeholder::Elapsed => write!(out, "{}", format::HumanDuration(self.elapsed())),
+	    Placeholder::Eta => match self.eta() {
+		Some(eta) => write!(out, "{}", format::HumanDuration(eta)),
+		None => out.write_str("--:--"),
+	    },
+	    Placeholder::Spinner => {
+		self.spinner.render_into(out, self.spinner_frame, self.spinner_width);
+		Ok(())
+	    },
+	    Placeholder::Stats => self.render_stats(out),
+	};
+    }
+
+    /// Refresh the display, returning any I/O error that occurs.
+    ///
+    /// If another thread is currently engaging in a display operation, nothing is written and `Ok(())` is returned.
+    pub fn try_refresh(&self) -> io::Result<()>
+    {
+	if self.errors.is_disabled() || self.is_lines() {
+	    return Ok(());
+	}
+	self.draw_with(Self::compose_line)
+    }
+
+    /// Blank the display, returning any I/O error that occurs.
+    ///
+    /// If another thread is currently engaging in a display operation, nothing is written and `Ok(())` is returned.
+    pub fn try_blank(&self) -> io::Result<()>
+    {
+	if self.errors.is_disabled() || self.is_lines() {
+	    return Ok(());
+	}
+	let (_, max_width) = self.widths();
+	self.draw_with(|_, frame| frame.blank(max_width))
+    }
+
+    /// Blank then print a line to the bar's output, and redisplay, returning any I/O error that occurs.
+    ///
+    /// The line and the redrawn bar are written as a single frame. In `DrawMode::Lines`, only the line is printed.
+    /// If another thread is currently engaging in a display operation, nothing is written and `Ok(())` is returned.
+    pub fn try_println(&self, string: &str) -> io::Result<()>
+    {
+	if self.errors.is_disabled() {
+	    return Ok(());
+	}
+	if self.is_lines() {
+	    return self.draw_with(|_, frame| {
+		frame.out.push_str(string);
+		frame.out.push('\n');
+	    });
+	}
+	let (_, max_width) = self.widths();
+	self.draw_with(|this, frame| {
+	    frame.blank(max_width);
+	    frame.out.push_str(string);
+	    frame.out.push('\n');
+	    this.compose_line(frame);
+	})
+    }
+
+    /// `try_refresh()`, with exclusive access.
+    ///
+    /// This advances the `{spinner}` of the template. Unless `force` is set, nothing is drawn if the bar was last drawn less than `draw_interval` ago and is not complete.
+    fn try_draw_mut(&mut self, force: bool) -> io::Result<()>
+    {
+	self.spinner_frame = self.spinner_frame.wrapping_add(1);
+	if self.errors.is_disabled() {
+	    return Ok(());
+	}
+	if self.is_lines() {
+	    return if self.log.due(self.progress, Instant::now()) {
+		self.draw_with(Self::compose_log)
+	    } else {
+		Ok(())
+	    };
+	}
+	if let Some(interval) = self.draw_interval {
+	    let now = Instant::now();
+	    if !force && self.progress < 1.0 && self.last_draw.is_some_and(|then| now.saturating_duration_since(then) < interval) {
+		self.undrawn = true;
+		return Ok(());
+	    }
+	    self.last_draw = Some(now);
+	}
+	self.undrawn = false;
+	self.draw_with(Self::compose_line)
+    }
+
+    /// `try_refresh()`, with exclusive access, limited by the draw interval.
+    #[inline]
+    fn try_refresh_mut(&mut self) -> io::Result<()>
+    {
+	self.try_draw_mut(false)
+    }
+
+    /// Advance the `{spinner}` and redraw, regardless of the draw interval, returning any I/O error that occurs.
+    ///
+    /// See `with_draw_interval()`.
+    #[inline]
+    pub fn try_tick(&mut self) -> io::Result<()>
+    {
+	self.try_draw_mut(true)
+    }
+
+    /// Advance the `{spinner}` and redraw, regardless of the draw interval.
+    ///
+    /// This also keeps `{elapsed}` and `{eta}` current while the progress is not changing.
+    #[inline]
+    pub fn tick(&mut self)
+    {
+	let res = self.try_tick();
+	self.errors.handle(res);
+    }
+
+    /// Set the title and redisplay, returning any I/O error that occurs.
+    pub fn try_set_title(&mut self, from: &str) -> io::Result<()>
+    {
+	self.title = from.to_string();
+	self.try_refresh_mut()
+    }
+
+    /// Set the progress and redisplay, returning any I/O error that occurs.
+    ///
+    /// If the bar has a length, the position is set to the corresponding fraction of it.
+    pub fn try_set_progress(&mut self, value: f64) -> io::Result<()>
+    {
+	if let Some(length) = self.length {
+	    self.position = (value * length as f64).round() as u64;
+	    self.throughput.record(self.position as f64, Instant::now());
+	}
+	if self.progress != value {
+	    self.progress = value;
+	    self.estimator.record(value, Instant::now());
+	    self.update();
+	}
+	self.try_refresh_mut()
+    }
+
+    /// Set the position and redisplay, returning any I/O error that occurs.
+    ///
+    /// See `set_length()`.
+    pub fn try_set_position(&mut self, position: u64) -> io::Result<()>
+    {
+	self.position = position;
+	self.sync_progress();
+	self.try_refresh_mut()
+    }
+
+    /// Advance the position by `n` and redisplay, returning any I/O error that occurs.
+    ///
+    /// See `set_length()`.
+    #[inline]
+    pub fn try_inc(&mut self, n: u64) -> io::Result<()>
+    {
+	self.try_set_position(self.position.saturating_add(n))
+    }
+
+    /// Set the length the position counts up to and redisplay, returning any I/O error that occurs.
+    ///
+    /// See `set_length()`.
+    pub fn try_set_length(&mut self, length: u64) -> io::Result<()>
+    {
+	self.length = Some(length);
+	self.sync_progress();
+	self.try_refresh_mut()
+    }
+
+    /// Set the position.
+    ///
+    /// See `set_length()`.
+    #[inline]
+    pub fn set_position(&mut self, position: u64)
+    {
+	let res = self.try_set_position(position);
+	self.errors.handle(res);
+    }
+
+    /// Advance the position by `n`.
+    ///
+    /// See `set_length()`.
+    #[inline]
+    pub fn inc(&mut self, n: u64)
+    {
+	let res = self.try_inc(n);
+	self.errors.handle(res);
+    }
+
+    /// Count progress as an integer position out of `length`.
+    ///
+    /// Once a bar has a length, its progress is always `position / length`, and the position and length are rendered after the percentage (e.g. `1,234/10,000`.)
+    /// The length can be changed at any time, e.g. when more work is discovered.
+    #[inline]
+    pub fn set_length(&mut self, length: u64)
+    {
+	let res = self.try_set_length(length);
+	self.errors.handle(res);
+    }
+
+    /// Redisplay after the position or length changed, unless the draw interval has not passed (see `with_draw_interval()`.)
+    pub(crate) fn refresh_mut(&mut self)
+    {
+	let res = self.try_refresh_mut();
+	self.errors.handle(res);
+    }
+
+    /// Update the max size and redisplay, returning any I/O error that occurs.
+    pub fn try_update_dimensions(&mut self, to: usize) -> io::Result<()>
+    {
+	self.max_width = to;
+	if self.errors.is_disabled() || self.is_lines() {
+	    return Ok(());
+	}
+	self.spinner_frame = self.spinner_frame.wrapping_add(1);
+	self.undrawn = false;
+	// Blank the old line and draw the new one in a single frame.
+	self.draw_with(|this, frame| {
+	    frame.blank(to);
+	    this.compose_line(frame);
+	})
+    }
+}
+
+impl<T: ?Sized + io::Write> Display for Bar<T>
+{
+    #[inline]
+    fn refresh(&self)
+    {
+	self.errors.handle(self.try_refresh());
+    }
+
+    #[inline]
+    fn blank(&self)
+    {
+	self.errors.handle(self.try_blank());
+    }
+
+    #[inline]
+    fn println(&self, string: &str)
+    {
+	self.errors.handle(self.try_println(string));
+    }
+
+    fn get_title(&self) -> &str
+    {
+	&self.title
+    }
+
+    #[inline]
+    fn set_title(&mut self, from: &str)
+    {
+	let res = self.try_set_title(from);
+	self.errors.handle(res);
+    }
+
+    #[inline]
+    fn update_dimensions(&mut self, to: usize)
+    {
+	let res = self.try_update_dimensions(to);
+	self.errors.handle(res);
+    }
+}
+
+impl<T: ?Sized + io::Write> ProgressBar for Bar<T>
+{
+    fn get_progress(&self) -> f64
+    {
+	self.progress
+    }
+    #[inline]
+    fn set_progress(&mut self, value: f64)
+    {
+	let res = self.try_set_progress(value);
+	self.errors.handle(res);
+    }
+}
+
+impl<T: io::Write> WithTitle for Bar<T>
+{
+    fn add_title(&mut self, string: impl AsRef<str>)
+    {
+	(*self).add_title(string.as_ref())
+    }
+    fn update(&mut self)
+    {
+	self.update();
+    }
+    fn complete(mut self)
+    {
+	let res = self.finish();
+	self.errors.handle(res);
+    }
+}
+
+const _:() = {
+    const fn declval<T>() -> Bar<T> {
+	unreachable!()
+    }
+    fn take_title(_: &impl WithTitle) {}
+    fn take_progress(_: &(impl ProgressBar + ?Sized)) {}
+    fn take_display(_: &(impl Display + ?Sized)) {}
+    fn test()
+    {
+	macro_rules! assert_is_bar {
+	    ($ty:path) => {
+		take_title(&declval::<$ty>());
+		take_progress(&declval::<$ty>());
+		take_display(&declval::<$ty>());
+	    }
+	}
+
+	assert_is_bar!(io::Stdout);
+	assert_is_bar!(std::fs::File);
+    }
+};
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    
+    #[test]
+    fn rendering_blanking()
+    {
+	let term = testing::VirtualTerminal::new(40, 3);
+	let mut bar: Bar<testing::VirtualTerminal> = Bar::with_size(term.clone(), 10, term.clone());
+	bar.set_progress(0.5);
+	assert_eq!(term.screen(), ["[=====     ]: 50.00%", "", ""]);
+	bar.blank();
+	assert_eq!(term.screen(), ["", "", ""]);
+	bar.set_progress(0.7);
+	bar.set_title("70 percent.");
+	bar.refresh();
+	assert_eq!(term.screen(), ["[=======   ]: 70.00% 70 percent.", "", ""]);
+	assert_eq!(term.cursor(), (0, 0));
+
+	bar.set_progress(0.2);
+	bar.set_title("a title much too long to fit on the line");
+	assert_eq!(term.screen()[0], "[==        ]: 20.00% a title much too...");
+	bar.complete().unwrap();
+	assert_eq!(term.screen(), ["[==        ]: 20.00% a title much too...", "", ""]);
+	assert_eq!(term.cursor(), (1, 0));
+
+	let term = testing::VirtualTerminal::new(40, 4);
+	let mut bar: Bar<testing::VirtualTerminal> = Bar::with_size(term.clone(), 10, term.clone());
+	bar.set_progress(0.5);
+	bar.println("printed above");
+	assert_eq!(term.screen(), ["printed above", "[=====     ]: 50.00%", "", ""]);
+	let mut writer = bar.print_writer();
+	io::Write::write_all(&mut writer, b"written\nin ").unwrap();
+	assert_eq!(term.screen(), ["printed above", "written", "[=====     ]: 50.00%", ""]);
+	io::Write::write_all(&mut writer, b"parts").unwrap();
+	drop(writer);
+	assert_eq!(term.screen(), ["written", "in parts", "[=====     ]: 50.00%", ""]);
+    }
+
+    #[test]
+    fn finish_modes()
+    {
+	let finish = |mode: FinishMode| {
+	    let term = testing::VirtualTerminal::new(40, 3);
+	    let mut bar: Bar<testing::VirtualTerminal> = Bar::with_size(term.clone(), 10, term.clone());
+	    bar.set_title("copy");
+	    bar.set_progress(0.5);
+	    bar.complete_with(mode).unwrap();
+	    (term.screen().swap_remove(0), term.cursor())
+	};
+	assert_eq!(finish(FinishMode::Leave), ("[=====     ]: 50.00% copy".to_owned(), (1, 0)));
+	assert_eq!(finish(FinishMode::Fill), ("[==========]: 100.00% copy".to_owned(), (1, 0)));
+	assert_eq!(finish(FinishMode::Clear), ("".to_owned(), (0, 0)));
+	assert_eq!(finish(FinishMode::Replace("copied 2 files".to_owned())), ("copied 2 files".to_owned(), (1, 0)));
+	assert_eq!(finish(FinishMode::Abandon), ("[=====     ]: 50.00% (abandoned) copy".to_owned(), (1, 0)));
+
+	let term = testing::VirtualTerminal::new(40, 3);
+	let mut bar: Bar<testing::VirtualTerminal> = Bar::with_size(term.clone(), 10, term.clone())
+	    .with_finish_mode(FinishMode::Replace("dropped".to_owned()));
+	bar.set_progress(0.5);
+	drop(bar);
+	assert_eq!(term.screen(), ["dropped", "", ""]);
+
+	let term = testing::VirtualTerminal::new(40, 3);
+	drop(Bar::<testing::VirtualTerminal>::with_size(term.clone(), 10, term.clone()));
+	assert_eq!(term.cursor(), (0, 0), "a bar that was never drawn should not finish");
+    }
+
+    #[test]
+    fn hiding_cursor()
+    {
+	let term = testing::VirtualTerminal::new(40, 3);
+	let mut bar: Bar<testing::VirtualTerminal> = Bar::with_size(term.clone(), 10, term.clone())
+	    .with_hide_cursor(true);
+	assert!(term.cursor_visible());
+	bar.set_progress(0.5);
+	assert!(!term.cursor_visible());
+	drop(bar);
+	assert!(term.cursor_visible(), "a dropped bar should show the cursor again");
+
+	let mut bar: Bar<testing::VirtualTerminal> = Bar::with_size(term.clone(), 10, term.clone());
+	bar.set_progress(0.5);
+	assert!(term.cursor_visible(), "hiding is off by default");
+    }
+
+    #[test]
+    fn throttling()
+    {
+	let term = testing::VirtualTerminal::new(40, 3);
+	let mut bar: Bar<testing::VirtualTerminal> = Bar::with_size(term.clone(), 10, term.clone())
+	    .with_draw_interval(Duration::from_secs(3600));
+	bar.set_progress(0.1);
+	bar.set_progress(0.5);
+	bar.set_title("skipped");
+	assert_eq!(term.screen()[0], "[=         ]: 10.00%");
+	bar.tick();
+	assert_eq!(term.screen()[0], "[=====     ]: 50.00% skipped");
+	bar.set_progress(1.0);
+	assert_eq!(term.screen()[0], "[==========]: 100.00% skipped");
+	bar.set_title("done");
+	bar.complete().unwrap();
+	assert_eq!(term.screen()[0], "[==========]: 100.00% done");
+	assert_eq!(term.cursor(), (1, 0));
+
+	let term = testing::VirtualTerminal::new(40, 3);
+	let bar = Bar::<testing::VirtualTerminal>::with_size(term.clone(), 10, term.clone()).into_handle(100);
+	let tick = bar.steady_tick(Duration::from_secs(3600));
+	bar.set_position(50);
+	assert_eq!(term.screen()[0], "");
+	tick.stop();
+	assert_eq!(term.screen()[0], "[=====     ]: 50.00% 50/100");
+    }
+
+    #[test]
+    fn resizing()
+    {
+	let term = testing::VirtualTerminal::new(40, 3);
+	let mut bar: Bar<testing::VirtualTerminal> = Bar::with_size(term.clone(), 10, term.clone());
+	bar.set_title("a title that fits");
+	assert_eq!(term.screen()[0], "[          ]: 0.00% a title that fits");
+	term.resize(30);
+	assert!(bar.fit());
+	assert_eq!(term.screen()[0], "[          ]: 0.00% a title...");
+    }
+
+    #[test]
+    fn creating_non_default_fd() {
+	#[cfg(feature="size")] 
+	let Some(_): Option<Bar<std::io::Stderr>> = Bar::try_new(std::io::stderr(), super::DEFAULT_SIZE) else { return };
 	#[cfg(not(feature="size"))]
 	let _: Bar<std::io::Stderr> = Bar::new(std::io::stderr(), super::DEFAULT_SIZE);
     }
+
+    #[test]
+    fn error_policies()
+    {
+	let (reader, writer) = io::pipe().unwrap();
+	drop(reader);
+
+	let mut bar: Bar<io::PipeWriter> = Bar::with_max(writer, 10, 30)
+	    .with_draw_mode(lines::DrawMode::Terminal)
+	    .with_error_policy(ErrorPolicy::Remember);
+	assert_eq!(bar.try_refresh().unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+	assert!(bar.take_error().is_none());
+	bar.set_progress(0.5);
+	assert_eq!(bar.take_error().map(|e| e.kind()), Some(io::ErrorKind::BrokenPipe));
+	assert!(bar.take_error().is_none());
+
+	bar.set_error_policy(ErrorPolicy::DisableOnBrokenPipe);
+	assert!(!bar.is_disabled());
+	bar.set_progress(0.7);
+	assert!(bar.is_disabled());
+	bar.try_refresh().unwrap();
+    }
+
+    #[test]
+    #[cfg(reactive)]
+    fn resizing_on_sigwinch()
+    {
+	let (master, slave) = reactive::open_pty(80).unwrap();
+	let bar: Bar<std::fs::File> = Bar::new(std::fs::File::from(slave), 50);
+	assert_eq!(bar.widths(), (50, 80));
+
+	reactive::resize_pty(&master, 30).unwrap();
+	assert_eq!(bar.widths(), (50, 80), "width should be cached until the terminal is resized");
+
+	// SAFETY: We installed a handler for `SIGWINCH` when creating the bar.
+	assert_eq!(unsafe { libc::raise(libc::SIGWINCH) }, 0);
+	assert_eq!(bar.widths(), (30, 30));
+    }
+
+    #[test]
+    fn position_and_length()
+    {
+	let (_reader, writer) = io::pipe().unwrap();
+	let mut bar: Bar<io::PipeWriter> = Bar::with_max(writer, 10, 60).with_length(10_000);
+	bar.set_position(1234);
+	assert_eq!(bar.get_progress(), 0.1234);
+	assert!(bar.line().starts_with("[=         ]: 12.34% 1,234/10,000 "));
+
+	bar.set_length(2468);
+	assert_eq!(bar.get_progress(), 0.5);
+	bar.set_progress(0.25);
+	assert_eq!(bar.position(), 617);
+	bar.inc(1);
+	assert_eq!(bar.position(), 618);
+
+	std::thread::sleep(Duration::from_millis(20));
+	bar.set_length(100_000);
+	assert!(bar.elapsed() >= Duration::from_millis(20), "a longer length should not reset the time elapsed");
+    }
+
+    #[test]
+    fn any_writer()
+    {
+	let mut out = Vec::new();
+	let mut bar: Bar<&mut Vec<u8>> = Bar::with_size(&mut out, 10, size::FixedSize(30));
+	assert_eq!(bar.widths(), (10, 30));
+	bar.set_progress(0.5);
+	bar.complete().unwrap();
+	let out = String::from_utf8(out).unwrap();
+	assert!(out.contains("[=====     ]: 50.00%"), "{out:?}");
+
+	let bar: Bar<io::Sink> = Bar::with_size(io::sink(), 10, size::FnSize(|| None));
+	assert_eq!(bar.widths(), (10, 30));
+	assert_eq!(bar.draw_mode(), lines::DrawMode::Lines);
+    }
+
+    #[test]
+    fn single_write_frames()
+    {
+	/// Records the length of every `write()`.
+	#[derive(Debug, Default)]
+	struct Writes(Vec<usize>);
+	impl io::Write for Writes
+	{
+	    fn write(&mut self, buf: &[u8]) -> io::Result<usize>
+	    {
+		self.0.push(buf.len());
+		Ok(buf.len())
+	    }
+	    fn flush(&mut self) -> io::Result<()>
+	    {
+		Ok(())
+	    }
+	}
+
+	let mut writes = Writes::default();
+	let mut bar: Bar<&mut Writes> = Bar::with_size(&mut writes, 10, size::FixedSize(40))
+	    .with_draw_mode(lines::DrawMode::Terminal)
+	    .with_length(100)
+	    .with_rate();
+	bar.set_position(50);
+	bar.blank();
+	bar.update_dimensions(30);
+	bar.complete().unwrap();
+	assert_eq!(writes.0.len(), 4, "{:?}", writes.0);
+    }
+
+    #[test]
+    fn line_mode()
+    {
+	use io::Read;
+	let (mut reader, writer) = io::pipe().unwrap();
+	let mut bar: Bar<io::PipeWriter> = Bar::with_max(writer, 10, 30)
+	    .with_length(100)
+	    .with_line_interval(lines::Interval { percent: 50.0, time: None });
+	assert_eq!(bar.draw_mode(), lines::DrawMode::Lines);
+	bar.set_title("copying");
+	for _ in 0..99 {
+	    bar.inc(1);
+	}
+	bar.refresh();
+	bar.complete().unwrap();
+
+	let mut log = String::new();
+	reader.read_to_string(&mut log).unwrap();
+	assert_eq!(log, "copying: 0% 0/100\ncopying: 50% 50/100\ncopying: 99% 99/100\n");
+    }
+
+    #[test]
+    fn wide_titles()
+    {
+	let (_reader, writer) = io::pipe().unwrap();
+	let mut bar: Bar<io::PipeWriter> = Bar::with_max(writer, 4, 24);
+	bar.set_title("日本語のタイトル");
+	assert_eq!(bar.line(), "[    ]: 0.00% 日本語... ");
+	bar.set_title("👨\u{200d}👩\u{200d}👧 family");
+	assert_eq!(bar.line(), "[    ]: 0.00% 👨\u{200d}👩\u{200d}👧 family ");
+	assert_eq!(width::width(&bar.line()), 24);
+    }
+
+    #[test]
+    fn bar_placeholders()
+    {
+	let (_reader, writer) = io::pipe().unwrap();
+	let mut bar: Bar<io::PipeWriter> = Bar::with_max(writer, 4, 40)
+	    .with_template("[{bar}] {pct:.0}%".parse().unwrap());
+	bar.set_progress(0.5);
+	assert_eq!(bar.line().trim_end(), "[==  ] 50%");
+	bar.set_template("{bracketed_bar} {pct:.0}%".parse().unwrap());
+	bar.set_style(style::BarStyle::SMOOTH);
+	assert_eq!(bar.line().trim_end(), "│██  │ 50%");
+    }
+
+    #[test]
+    fn colors()
+    {
+	use color::{BarColors, Color, ColorLevel, Style};
+	let (_reader, writer) = io::pipe().unwrap();
+	let colors = BarColors { filled: Style::new().fg(Color::GREEN), percent: Style::new().bold(), ..Default::default() };
+	let mut bar: Bar<io::PipeWriter> = Bar::with_max(writer, 4, 20).with_colors(colors);
+	bar.set_progress(0.5);
+	bar.set_color_level(ColorLevel::None);
+	assert_eq!(bar.line(), "[==  ]: 50.00%      ");
+
+	bar.set_color_level(ColorLevel::Ansi16);
+	let line = bar.line();
+	assert_eq!(line, "[\x1B[32m==\x1B[0m  ]: \x1B[1m50.00\x1B[0m%      ");
+	assert_eq!(width::width(&line), 20);
+    }
 }
@@ -0,0 +1,165 @@
+//! Reacting to terminal resizes with `SIGWINCH`.
+//!
+//! Requires the `reactive` feature, and is only available on linux.
+//!
+//! Indicators that fit themselves to the terminal cache its width, and only query it again after the terminal has been resized.
+//! A `SIGWINCH` handler is installed the first time such an indicator is created, which marks every live indicator's cached width as stale.
+
+use std::{
+    mem,
+    sync::{
+	Once,
+	atomic::{AtomicUsize, Ordering},
+    },
+};
+
+/// Incremented every time the terminal is resized.
+static GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+/// The `SIGWINCH` handler that was installed before ours, so that we do not steal the signal from it.
+static PREVIOUS_HANDLER: AtomicUsize = AtomicUsize::new(libc::SIG_DFL);
+/// The `sa_flags` of `PREVIOUS_HANDLER`.
+static PREVIOUS_FLAGS: AtomicUsize = AtomicUsize::new(0);
+
+static INSTALL: Once = Once::new();
+
+extern "C" fn on_sigwinch(sig: libc::c_int, info: *mut libc::siginfo_t, ctx: *mut libc::c_void)
+{
+    // Only async-signal-safe operations are allowed in here.
+    GENERATION.fetch_add(1, Ordering::Release);
+
+    let previous = PREVIOUS_HANDLER.load(Ordering::Acquire);
+    if previous == libc::SIG_DFL || previous == libc::SIG_IGN {
+	return;
+    }
+    // SAFETY: `previous` is the address of a handler that was installed for this signal before ours, with the signature given by its flags.
+    unsafe {
+	if (PREVIOUS_FLAGS.load(Ordering::Acquire) as libc::c_int) & libc::SA_SIGINFO != 0 {
+	    let previous: extern "C" fn(libc::c_int, *mut libc::siginfo_t, *mut libc::c_void) = mem::transmute(previous);
+	    previous(sig, info, ctx);
+	} else {
+	    let previous: extern "C" fn(libc::c_int) = mem::transmute(previous);
+	    previous(sig);
+	}
+    }
+}
+
+/// Install the `SIGWINCH` handler, if it has not been installed already.
+///
+/// This is done automatically when an indicator that fits itself to the terminal is created.
+/// Any handler installed before this one is still called when the signal is received.
+pub fn install()
+{
+    INSTALL.call_once(|| {
+	// SAFETY: The handler only performs async-signal-safe operations, and `sigaction` is passed valid pointers.
+	unsafe {
+	    let mut action: libc::sigaction = mem::zeroed();
+	    action.sa_sigaction = on_sigwinch as *const () as libc::sighandler_t;
+	    action.sa_flags = libc::SA_SIGINFO | libc::SA_RESTART;
+	    libc::sigemptyset(&mut action.sa_mask);
+
+	    let mut previous: libc::sigaction = mem::zeroed();
+	    if libc::sigaction(libc::SIGWINCH, &action, &mut previous) == 0 {
+		PREVIOUS_FLAGS.store(previous.sa_flags as usize, Ordering::Release);
+		PREVIOUS_HANDLER.store(previous.sa_sigaction, Ordering::Release);
+	    }
+	}
+    });
+}
+
+/// Mark every live indicator's cached terminal width as stale, as if `SIGWINCH` had been received.
+#[inline]
+pub fn notify_resize()
+{
+    GENERATION.fetch_add(1, Ordering::Release);
+}
+
+/// The number of times the terminal has been resized (or `notify_resize()` called) since the program started.
+#[inline]
+pub fn generation() -> usize
+{
+    GENERATION.load(Ordering::Acquire)
+}
+
+/// A terminal width that is queried again only after a resize.
+#[derive(Debug)]
+pub(crate) struct CachedWidth
+{
+    generation: AtomicUsize,
+    width: AtomicUsize,
+}
+
+impl CachedWidth
+{
+    /// The width has never been queried.
+    const STALE: usize = usize::MAX;
+
+    /// Create a new, stale, cached width. Installs the `SIGWINCH` handler if needed.
+    pub fn new() -> Self
+    {
+	install();
+	Self {
+	    generation: AtomicUsize::new(Self::STALE),
+	    width: AtomicUsize::new(0),
+	}
+    }
+
+    /// Get the cached width, or run `query` to get a new one if the terminal has been resized since it was cached.
+    ///
+    /// If `query` fails, nothing is cached and it will be run again next time.
+    pub fn get_or_query(&self, query: impl FnOnce() -> Option<usize>) -> Option<usize>
+    {
+	let current = generation();
+	if self.generation.load(Ordering::Acquire) == current {
+	    return Some(self.width.load(Ordering::Relaxed));
+	}
+	let width = query()?;
+	self.width.store(width, Ordering::Relaxed);
+	self.generation.store(current, Ordering::Release);
+	Some(width)
+    }
+
+    /// Mark this width as stale.
+    #[inline]
+    pub fn invalidate(&self)
+    {
+	self.generation.store(Self::STALE, Ordering::Release);
+    }
+}
+
+impl Default for CachedWidth
+{
+    #[inline]
+    fn default() -> Self
+    {
+	Self::new()
+    }
+}
+
+/// Open a new pseudoterminal pair with the specified number of columns.
+#[cfg(test)]
+pub(crate) fn open_pty(cols: u16) -> std::io::Result<(std::os::fd::OwnedFd, std::os::fd::OwnedFd)>
+{
+    use std::os::fd::FromRawFd;
+    let size = libc::winsize { ws_row: 24, ws_col: cols, ws_xpixel: 0, ws_ypixel: 0 };
+    let (mut master, mut slave) = (-1, -1);
+    // SAFETY: All pointers are valid for the duration of the call, and on success the returned fds are owned by us.
+    unsafe {
+	if libc::openpty(&mut master, &mut slave, std::ptr::null_mut(), std::ptr::null(), &size) != 0 {
+	    return Err(std::io::Error::last_os_error());
+	}
+	Ok((std::os::fd::OwnedFd::from_raw_fd(master), std::os::fd::OwnedFd::from_raw_fd(slave)))
+    }
+}
+
+/// Set the number of columns of a pseudoterminal.
+#[cfg(test)]
+pub(crate) fn resize_pty(pty: &impl std::os::fd::AsRawFd, cols: u16) -> std::io::Result<()>
+{
+    let size = libc::winsize { ws_row: 24, ws_col: cols, ws_xpixel: 0, ws_ypixel: 0 };
+    // SAFETY: `size` is valid for the duration of the call.
+    if unsafe { libc::ioctl(pty.as_raw_fd(), libc::TIOCSWINSZ, &size) } != 0 {
+	return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
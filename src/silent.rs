@@ -7,13 +7,14 @@ use super::*;
 
 /// An implementor for the `Display`, `ProgressBar`, `Spinner`, and `WithTitle` that does nothing.
 ///
-/// It also implements `Display::println()` and `Display::eprintln()` to do nothing as well.
+/// It also implements `Display::println()`, `Display::println_stdout()` and `Display::eprintln()` to do nothing as well.
 #[derive(Debug)]
 pub struct Silent;
 
 impl Display for Silent
 {
     #[inline] fn println(&self, _: &str){}
+    #[inline] fn println_stdout(&self, _: &str){}
     #[inline] fn eprintln(&self, _: &str){}
     #[inline] fn refresh(&self){}
     #[inline] fn blank(&self){}
@@ -74,6 +75,12 @@ where T: Display
 	    this.println(string);
 	}
     }
+    fn println_stdout(&self, string: &str)
+    {
+	if let Self::Loud(this) = self {
+	    this.println_stdout(string);
+	}
+    }
     fn eprintln(&self, string: &str)
     {
 	if let Self::Loud(this) = self {
@@ -215,11 +222,7 @@ impl<T> MaybeSilent<T>
     /// Is this the silent variant?
     #[inline] pub fn is_silent(&self) -> bool
     {
-	if let Self::Silent = self {
-	    true
-	} else {
-	    false
-	}
+	matches!(self, Self::Silent)
     }
     /// Create a new `MaybeSilent` with a value.
     pub const fn new_some(value: T) -> Self
@@ -0,0 +1,197 @@
+//! Querying the size of the terminal an indicator draws to.
+//!
+//! A `progress::Bar` only needs its output to be `io::Write`. What it knows about the terminal behind the output (its width, and whether there is one at all) comes from a `SizeQuery`:
+//! * `FdSize` queries the terminal behind a file descriptor, and is used by the constructors that take an `AsFd` output.
+//! * `FixedSize` is a terminal of a fixed width, e.g. for rendering into a buffer.
+//! * `NoTerminal` is not a terminal at all, e.g. for a socket or a log file.
+//! * `FnSize` asks a closure.
+
+use super::lines;
+#[cfg(reactive)]
+use super::reactive;
+use std::{
+    fmt,
+    io::{
+	self,
+	IsTerminal,
+    },
+    os::fd::{
+	AsFd,
+	OwnedFd,
+    },
+};
+
+/// A source of the size of the terminal an indicator draws to.
+pub trait SizeQuery: fmt::Debug + Send + Sync
+{
+    /// The width of the terminal in columns, if the output is one and its width can be found.
+    fn width(&self) -> Option<usize>;
+
+    /// Is the output a terminal?
+    ///
+    /// This decides the defaults for colours (see `color::ColorLevel::detect()`) and drawing (see `lines::DrawMode::detect()`.)
+    fn is_terminal(&self) -> bool;
+
+    /// The mode an indicator drawing to the output is drawn in by default.
+    ///
+    /// By default, `Terminal` if the output `is_terminal()` and `Lines` otherwise. Only `FdSize` also looks at the `CI` environment variable (see `lines::DrawMode::detect()`), since any other query says outright whether the output is a terminal.
+    #[inline]
+    fn draw_mode(&self) -> lines::DrawMode
+    {
+	if self.is_terminal() { lines::DrawMode::Terminal } else { lines::DrawMode::Lines }
+    }
+
+    /// Forget any cached width, so the next call to `width()` queries it again.
+    #[inline]
+    fn invalidate(&self) {}
+}
+
+/// The size of the terminal behind a file descriptor.
+///
+/// The descriptor is duplicated, so this does not borrow the output.
+/// With feature `reactive`, the width is cached until the terminal is resized.
+/// Without feature `size`, the width is never known.
+#[derive(Debug)]
+pub struct FdSize
+{
+    fd: OwnedFd,
+    #[cfg(reactive)]
+    cached: reactive::CachedWidth,
+}
+
+impl FdSize
+{
+    /// Query the terminal behind `fd`.
+    ///
+    /// # Errors
+    /// If the descriptor cannot be duplicated.
+    #[inline]
+    pub fn new(fd: &(impl AsFd + ?Sized)) -> io::Result<Self>
+    {
+	fd.as_fd().try_clone_to_owned().map(Self::from_owned)
+    }
+
+    /// Query the terminal behind `fd`.
+    #[inline]
+    pub fn from_owned(fd: OwnedFd) -> Self
+    {
+	Self {
+	    fd,
+	    #[cfg(reactive)]
+	    cached: Default::default(),
+	}
+    }
+
+    /// Query the terminal behind `fd`, or `NoTerminal` if the descriptor cannot be duplicated.
+    pub(crate) fn boxed(fd: &(impl AsFd + ?Sized)) -> Box<dyn SizeQuery>
+    {
+	match Self::new(fd) {
+	    Ok(size) => Box::new(size),
+	    Err(_) => Box::new(NoTerminal),
+	}
+    }
+
+    #[cfg(feature="size")]
+    #[inline]
+    fn query(&self) -> Option<usize>
+    {
+	terminal_size::terminal_size_of(&self.fd).map(|(terminal_size::Width(tw), _)| usize::from(tw))
+    }
+}
+
+impl SizeQuery for FdSize
+{
+    fn width(&self) -> Option<usize>
+    {
+	#[cfg(reactive)]
+	return self.cached.get_or_query(|| self.query());
+	#[cfg(all(feature="size", not(reactive)))]
+	return self.query();
+	#[cfg(not(feature="size"))]
+	return None;
+    }
+
+    #[inline]
+    fn is_terminal(&self) -> bool
+    {
+	self.fd.is_terminal()
+    }
+
+    #[inline]
+    fn draw_mode(&self) -> lines::DrawMode
+    {
+	lines::DrawMode::detect(self.is_terminal())
+    }
+
+    #[inline]
+    fn invalidate(&self)
+    {
+	#[cfg(reactive)]
+	self.cached.invalidate();
+    }
+}
+
+/// A terminal that is always `.0` columns wide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FixedSize(pub usize);
+
+impl SizeQuery for FixedSize
+{
+    #[inline]
+    fn width(&self) -> Option<usize>
+    {
+	Some(self.0)
+    }
+    #[inline]
+    fn is_terminal(&self) -> bool
+    {
+	true
+    }
+}
+
+/// Not a terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct NoTerminal;
+
+impl SizeQuery for NoTerminal
+{
+    #[inline]
+    fn width(&self) -> Option<usize>
+    {
+	None
+    }
+    #[inline]
+    fn is_terminal(&self) -> bool
+    {
+	false
+    }
+}
+
+/// A terminal whose width is queried by calling `.0`.
+///
+/// The output is considered a terminal whenever the closure returns a width.
+#[derive(Clone, Copy)]
+pub struct FnSize<F>(pub F);
+
+impl<F> fmt::Debug for FnSize<F>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+	f.debug_tuple("FnSize").finish_non_exhaustive()
+    }
+}
+
+impl<F> SizeQuery for FnSize<F>
+where F: Fn() -> Option<usize> + Send + Sync
+{
+    #[inline]
+    fn width(&self) -> Option<usize>
+    {
+	(self.0)()
+    }
+    #[inline]
+    fn is_terminal(&self) -> bool
+    {
+	self.width().is_some()
+    }
+}
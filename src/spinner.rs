@@ -1,7 +1,24 @@
 //! A simple character spinner for bars with no known size
 
 use super::*;
-use std::io;
+use error::ErrorState;
+use std::{
+    borrow::Cow,
+    io,
+    mem::ManuallyDrop,
+    os::fd::AsFd,
+    sync::{
+	Arc,
+	OnceLock,
+	atomic::{
+	    AtomicBool,
+	    AtomicUsize,
+	    Ordering,
+	},
+    },
+    thread,
+    time::Duration,
+};
 
 /// A single character spinner with optional title that can be told to spin whenever it wants. It implements `Spinner` trait, and is the default spinner.
 ///
@@ -15,60 +32,262 @@ use std::io;
 /// let mut spin = Spin::default(); //Default new spinner without a title.
 /// ```
 ///
+/// Spinners created with `new()` or `with_title()` can draw to any `io::Write` (e.g. a `Vec<u8>`), which is assumed not to be a terminal. To draw to a file descriptor that may be one, use `with_fd()`, or give the size of the terminal with `with_size()`.
+///
 /// # How it looks
 /// It renders in the terminal like:
 /// `This is a spinner /`
 ///
+/// If the width of the terminal is known, a title too long to fit on the line is truncated with `...`.
+///
 /// # Thread `Sync`safety
 /// This type is safely `Sync` (where `T` is), the behaviour is defined to prevent overlapping writes to `T`.
 /// Though it is *advised* to not render a `Spin` from more than a single thread, you still safely can.
 ///
 /// A display operation on one thread will cause any other threads attempting on to silently and safely abort their display attempt before anything is written to output.
+///
+/// # Finishing
+/// `complete()` removes the spinner's frame. To show how the work it was spinning for went, finish it with a `Status` instead, which replaces its line with a symbol and a message:
+/// ```rust
+/// # use termprogress::prelude::*;
+/// let spin = Spin::with_title(std::io::sink(), "deploying", Default::default());
+/// // *does work*
+/// spin.succeed("deployed").unwrap(); // `✔ deployed`, in green if the output supports colour.
+/// ```
+/// The symbols and colours are set with `with_marks()`, see `Marks`.
+///
+/// A spinner that has been drawn is finished according to its `progress::FinishMode` when it is dropped without being completed, so the line is ended (by default, with the frame removed) instead of being left for the next prompt to overwrite. See `with_finish_mode()`.
+///
+/// # Spinning in the background
+/// A spinner shared in an `Arc` can be ticked at a fixed rate by a background thread with `steady_tick()`, so it keeps spinning while the thread using it is blocked:
+/// ```rust
+/// # use termprogress::prelude::*;
+/// # use std::{sync::Arc, time::Duration};
+/// let spin = Arc::new(Spin::with_title(std::io::sink(), "waiting", Default::default()));
+/// let ticker = spin.steady_tick(Duration::from_millis(100)); // 10 frames per second.
+/// spin.println("still working"); // Lines can still be printed while it spins.
+/// // *blocks on I/O*
+/// ticker.stop();
+/// Arc::try_unwrap(spin).unwrap().complete_with("done").unwrap();
+/// ```
+#[derive(Debug)]
 pub struct Spin<T: ?Sized = DefaultOutputDevice>/*<T: ?Sized = DefaultOutputDevice>*/ //TODO: <- implement same as `Bar
 {
     title: String,
-    current: char,
-    chars: wheel::WheelIntoIter,
-    output: AtomicRefCell<T>,
+    wheel: wheel::Wheel,
+    /// The index of the current frame of `wheel`.
+    frame: AtomicUsize,
+    /// The number of columns every frame of `wheel` is drawn in.
+    frame_width: usize,
+    /// Set while a `tick()` is drawing, so `println()` waits for it instead of giving up.
+    ticking: AtomicBool,
+    marks: Marks,
+    color_level: OnceLock<color::ColorLevel>,
+    size: Box<dyn size::SizeQuery>,
+    errors: ErrorState,
+    /// Has anything been written to the output?
+    drawn: AtomicBool,
+    finish_mode: progress::FinishMode,
+    finished: bool,
+    /// Set if the output is a terminal, so the panic hook can blank the spinner's line (see `cleanup`.)
+    registration: Option<cleanup::Registration>,
+    cursor: cleanup::Cursor,
+    /// Finishes the spinner when it is dropped, see `Bar`. Taken by `into_inner()` along with `output`, so `Drop` knows whether `output` is still there to drop.
+    finish_on_drop: Option<fn(&mut Self)>,
+    output: ManuallyDrop<AtomicRefCell<T>>,
 }
 
 impl Spin
 {
-    /// Create a new spinner with title and wheel writing to `stdout`.
+    /// Create a new spinner with title and wheel writing to the default output stream (see `default_output_stream()`).
     ///
     /// To give it the default wheel, you can pass `whl` `Default::default()` to use the default one.
     #[inline] 
     pub fn with_title_default(title: &str, whl: wheel::Wheel) -> Self
     {
-	Self::with_title(create_default_output_device(), title, whl)
+	Self::with_fd(create_default_output_device(), title, whl)
     }
     
-    /// Create a new blank spinner with a wheel writing to `stdout`.
+    /// Create a new blank spinner with a wheel writing to the default output stream (see `default_output_stream()`).
     ///
     /// # Example
     /// ```rust
     /// # use termprogress::prelude::*;
-    ///  Spin::new_default(Default::default()); // Create a spinner with the default wheel ('|/-\') that writes to stdout.
+    ///  Spin::new_default(Default::default()); // Create a spinner with the default wheel ('|/-\') that writes to the default output stream.
     /// ```
     #[inline] 
     pub fn new_default(whl: wheel::Wheel) -> Self
     {
-	Self::new(create_default_output_device(), whl)
+	Self::with_fd(create_default_output_device(), "", whl)
     }
 }
 
 impl<T> Spin<T>
 {
     /// Return the backing write object
+    ///
+    /// The spinner is not finished, so if it hid the cursor, it is not shown again.
     #[inline] 
-    pub fn into_inner(self) -> T
+    pub fn into_inner(mut self) -> T
+    {
+	self.finish_on_drop = None;
+	// SAFETY: `finish_on_drop` is gone, so `drop()` will neither use nor drop `output` again.
+	unsafe { ManuallyDrop::take(&mut self.output) }.into_inner()
+    }
+}
+
+impl<T> Spin<T>
+{
+    /// Set the policy for handling I/O errors that occur during infallible display operations.
+    #[inline]
+    pub fn with_error_policy(mut self, policy: ErrorPolicy) -> Self
+    {
+	self.set_error_policy(policy);
+	self
+    }
+
+    /// Show each `Status` the spinner is finished with using `marks`.
+    #[inline]
+    pub fn with_marks(mut self, marks: Marks) -> Self
+    {
+	self.marks = marks;
+	self
+    }
+
+    /// Render colours at `level`, instead of detecting it from the environment and output.
+    #[inline]
+    pub fn with_color_level(mut self, level: color::ColorLevel) -> Self
+    {
+	self.set_color_level(level);
+	self
+    }
+
+    /// Finish the spinner's line according to `mode` when it is dropped without being completed.
+    #[inline]
+    pub fn with_finish_mode(mut self, mode: progress::FinishMode) -> Self
+    {
+	self.set_finish_mode(mode);
+	self
+    }
+
+    /// Hide the cursor from when the spinner is first drawn until it is finished or dropped.
+    #[inline]
+    pub fn with_hide_cursor(mut self, hide: bool) -> Self
+    {
+	self.set_hide_cursor(hide);
+	self
+    }
+}
+
+impl<T: io::Write + AsFd> Spin<T>
+{
+    /// Create a new spinner with title and wheel writing to the file descriptor `output`.
+    ///
+    /// Unlike with `with_title()`, whether `output` is a terminal is detected (see `color_level()`), the spinner is restored by the panic hook installed with `cleanup::install_panic_hook()` if it is, and it shares the cursor with the other indicators drawing to the same file (see `set_hide_cursor()`.)
+    /// ```rust
+    /// # use termprogress::prelude::*;
+    /// let spin = Spin::with_fd(std::io::stdout(), "loading", Default::default());
+    /// ```
+    pub fn with_fd(output: T, title: &str, whl: wheel::Wheel) -> Self
     {
-	self.output.into_inner()
+	let size = size::FdSize::boxed(&output);
+	let (registration, stream) = (cleanup::Registration::new(&output), cleanup::StreamId::of(&output));
+	let mut this = Self::sized(output, title, whl, size);
+	this.registration = registration;
+	this.cursor.set_stream(stream);
+	this
     }
 }
 
 impl<T: ?Sized> Spin<T>
 {
+    /// Set the policy for handling I/O errors that occur during infallible display operations.
+    ///
+    /// See `ErrorPolicy`.
+    #[inline]
+    pub fn set_error_policy(&mut self, policy: ErrorPolicy)
+    {
+	self.errors.set_policy(policy);
+    }
+
+    /// Take the most recent I/O error that occured during a display operation.
+    ///
+    /// Errors are only remembered with `ErrorPolicy::Remember`.
+    #[inline]
+    pub fn take_error(&self) -> Option<io::Error>
+    {
+	self.errors.take()
+    }
+
+    /// Show each `Status` the spinner is finished with using `marks`.
+    #[inline]
+    pub fn set_marks(&mut self, marks: Marks)
+    {
+	self.marks = marks;
+    }
+
+    /// The marks each `Status` the spinner is finished with is shown with.
+    #[inline]
+    pub fn marks(&self) -> &Marks
+    {
+	&self.marks
+    }
+
+    /// Render colours at `level`, instead of detecting it from the environment and output.
+    #[inline]
+    pub fn set_color_level(&mut self, level: color::ColorLevel)
+    {
+	self.color_level = OnceLock::from(level);
+    }
+
+    /// Finish the spinner's line according to `mode` when it is dropped without being completed.
+    #[inline]
+    pub fn set_finish_mode(&mut self, mode: progress::FinishMode)
+    {
+	self.finish_mode = mode;
+    }
+
+    /// What the spinner leaves on its line when it is dropped without being completed.
+    #[inline]
+    pub fn finish_mode(&self) -> &progress::FinishMode
+    {
+	&self.finish_mode
+    }
+
+    /// Hide the cursor from when the spinner is first drawn until it is finished or dropped.
+    ///
+    /// See `cleanup` for how bars and spinners drawing to the same terminal share it.
+    #[inline]
+    pub fn set_hide_cursor(&mut self, hide: bool)
+    {
+	self.cursor.set_hide(hide);
+    }
+
+    /// Does the spinner hide the cursor while it is drawn?
+    #[inline]
+    pub fn hide_cursor(&self) -> bool
+    {
+	self.cursor.hides()
+    }
+
+    /// The level colours are rendered at.
+    ///
+    /// Unless set with `set_color_level()`, it is detected from the environment the first time it is needed (see `color::ColorLevel::detect()`.)
+    /// Only outputs given to `with_fd()` (or the default output stream) are detected as terminals, or those given to `with_size()` whose `size::SizeQuery` says so.
+    pub fn color_level(&self) -> color::ColorLevel
+    {
+	*self.color_level.get_or_init(|| color::ColorLevel::detect(self.size.is_terminal()))
+    }
+
+    /// Has rendering been disabled by the error policy?
+    ///
+    /// When disabled, all display operations (including the `try_*` family) do nothing and succeed.
+    #[inline]
+    pub fn is_disabled(&self) -> bool
+    {
+	self.errors.is_disabled()
+    }
+
     /// Get a mutable reference to the inner object
     #[inline] 
     pub fn inner_mut(&mut self) -> &mut T
@@ -94,15 +313,41 @@ impl<T: io::Write> Spin<T>
     /// Create a new spinner with title and wheel writing to `output`.
     ///
     /// To give it the default wheel, you can pass `whl` `Default::default()` to use the default one.
+    /// The output is assumed not to be a terminal, see `with_fd()`.
+    #[inline]
     pub fn with_title(output: T, title: &str, whl: wheel::Wheel) -> Self
     {
-	let mut chars = whl.into_iter();
-	let current = chars.next().unwrap();
+	Self::sized(output, title, whl, Box::new(size::NoTerminal))
+    }
+
+    /// Create a new spinner with title and wheel writing to any `output`, where the size of the terminal (if there is one) is queried from `size`.
+    ///
+    /// See `progress::Bar::with_size()`.
+    #[inline]
+    pub fn with_size(output: T, title: &str, whl: wheel::Wheel, size: impl size::SizeQuery + 'static) -> Self
+    {
+	Self::sized(output, title, whl, Box::new(size))
+    }
+
+    fn sized(output: T, title: &str, whl: wheel::Wheel, size: Box<dyn size::SizeQuery>) -> Self
+    {
 	Self {
 	    title: title.to_string(),
-	    current,
-	    chars,
-	    output: AtomicRefCell::new(output)
+	    frame_width: whl.width(),
+	    wheel: whl,
+	    frame: AtomicUsize::new(1),
+	    ticking: AtomicBool::new(false),
+	    marks: Default::default(),
+	    color_level: OnceLock::new(),
+	    size,
+	    errors: Default::default(),
+	    drawn: AtomicBool::new(false),
+	    finish_mode: Default::default(),
+	    finished: false,
+	    registration: None,
+	    cursor: Default::default(),
+	    finish_on_drop: Some(Self::finish_on_drop),
+	    output: ManuallyDrop::new(output.into()),
 	}
     }
     
@@ -111,31 +356,174 @@ impl<T: io::Write> Spin<T>
     /// # Example
     /// ```rust
     /// # use termprogress::prelude::*;
-    ///  Spin::new(std::io::stdout(), Default::default()); // Create a spinner with the default wheel ('|/-\') that writes to stdout.
+    /// let mut spin = Spin::new(Vec::new(), Default::default()); // Create a spinner with the default wheel ('|/-\\') that writes to a `Vec<u8>`.
+    /// spin.bump();
+    /// assert_eq!(spin.into_inner(), b"\r \\");
     /// ```
+    #[inline]
     pub fn new(output: T, whl: wheel::Wheel) -> Self
     {
-	let mut chars = whl.into_iter();
-	let current = chars.next().unwrap();
-	Self {
-	    title: String::new(),
-	    current,
-	    chars,
-	    output: output.into()
-	}
+	Self::with_title(output, "", whl)
     }
 
-    /// Consume the spinner and complete it. Removes the spin character.
+    /// Consume the spinner and complete it. Removes the spin frame.
     pub fn complete(self) -> io::Result<()> {
-	let mut output = self.output.into_inner();
-	writeln!(&mut output, "{} ", (8u8 as char))
+	self.complete_with("")
     }
     
-    /// Consume the spinner and complete it with a message. Removes the spin character and then prints the message.
-    pub fn complete_with(self, msg: &str) -> io::Result<()>
+    /// Consume the spinner and complete it with a message. Removes the spin frame and then prints the message.
+    pub fn complete_with(mut self, msg: &str) -> io::Result<()>
+    {
+	let show = self.settle();
+	if self.errors.is_disabled() {
+	    return Ok(());
+	}
+	let output = self.output.get_mut();
+	draw_complete(output, self.frame_width, msg)?;
+	draw_cursor_shown(output, show)
+    }
+
+    /// Consume the spinner and finish it with `status`, replacing its line with the status's mark and `msg`.
+    ///
+    /// If `msg` is empty, the title is printed instead. See `Marks`.
+    pub fn finish(mut self, status: Status, msg: &str) -> io::Result<()>
+    {
+	let mark = self.marks.get(status);
+	let mut line = String::new();
+	mark.style.paint(self.color_level(), &mark.symbol, &mut line);
+	line.push(' ');
+	line.push_str(if msg.is_empty() { &self.title } else { msg });
+	let width = self.line_width();
+
+	let show = self.settle();
+	if self.errors.is_disabled() {
+	    return Ok(());
+	}
+	let output = self.output.get_mut();
+	draw_blank(output, width)?;
+	writeln!(output, "{}", line)?;
+	draw_cursor_shown(output, show)
+    }
+
+    /// Consume the spinner and finish it with `Status::Success`, e.g. `✔ msg`.
+    #[inline]
+    pub fn succeed(self, msg: &str) -> io::Result<()>
+    {
+	self.finish(Status::Success, msg)
+    }
+
+    /// Consume the spinner and finish it with `Status::Failure`, e.g. `✖ msg`.
+    #[inline]
+    pub fn fail(self, msg: &str) -> io::Result<()>
+    {
+	self.finish(Status::Failure, msg)
+    }
+
+    /// Consume the spinner and finish it with `Status::Warning`, e.g. `⚠ msg`.
+    #[inline]
+    pub fn warn(self, msg: &str) -> io::Result<()>
+    {
+	self.finish(Status::Warning, msg)
+    }
+
+    /// Consume the spinner and finish it with `Status::Info`, e.g. `ℹ msg`.
+    #[inline]
+    pub fn info(self, msg: &str) -> io::Result<()>
+    {
+	self.finish(Status::Info, msg)
+    }
+}
+
+/// How the work a spinner was spinning for went, see `Spin::finish()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Status
+{
+    Success,
+    Failure,
+    Warning,
+    Info,
+}
+
+/// The symbol a finished spinner's line starts with for a `Status`, and its colour.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Mark
+{
+    pub symbol: Cow<'static, str>,
+    pub style: color::Style,
+}
+
+impl Mark
+{
+    /// A mark of `symbol` in `style`.
+    #[inline]
+    pub const fn new(symbol: &'static str, style: color::Style) -> Self
+    {
+	Self {
+	    symbol: Cow::Borrowed(symbol),
+	    style,
+	}
+    }
+}
+
+/// The `Mark` for each `Status` a spinner can be finished with.
+///
+/// # Sets
+/// * `Marks::UNICODE`: `✔` `✖` `⚠` `ℹ`
+/// * `Marks::ASCII`: `+` `x` `!` `i`, for terminals that cannot display Unicode.
+///
+/// Both colour success green, failure red, warning yellow and info blue.
+/// The default is `Marks::unicode_or_ascii()`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Marks
+{
+    pub success: Mark,
+    pub failure: Mark,
+    pub warning: Mark,
+    pub info: Mark,
+}
+
+impl Marks
+{
+    pub const UNICODE: Self = Self {
+	success: Mark::new("✔", color::Style::new().fg(color::Color::GREEN)),
+	failure: Mark::new("✖", color::Style::new().fg(color::Color::RED)),
+	warning: Mark::new("⚠", color::Style::new().fg(color::Color::YELLOW)),
+	info: Mark::new("ℹ", color::Style::new().fg(color::Color::BLUE)),
+    };
+
+    pub const ASCII: Self = Self {
+	success: Mark::new("+", color::Style::new().fg(color::Color::GREEN)),
+	failure: Mark::new("x", color::Style::new().fg(color::Color::RED)),
+	warning: Mark::new("!", color::Style::new().fg(color::Color::YELLOW)),
+	info: Mark::new("i", color::Style::new().fg(color::Color::BLUE)),
+    };
+
+    /// `UNICODE` if the locale supports UTF-8 (according to `LC_ALL`, `LC_CTYPE` or `LANG`), `ASCII` otherwise.
+    #[inline]
+    pub fn unicode_or_ascii() -> Self
+    {
+	if style::utf8_locale() { Self::UNICODE } else { Self::ASCII }
+    }
+
+    /// The mark for `status`.
+    #[inline]
+    pub fn get(&self, status: Status) -> &Mark
+    {
+	match status {
+	    Status::Success => &self.success,
+	    Status::Failure => &self.failure,
+	    Status::Warning => &self.warning,
+	    Status::Info => &self.info,
+	}
+    }
+}
+
+impl Default for Marks
+{
+    #[inline]
+    fn default() -> Self
     {
-	let mut output = self.output.into_inner();
-	writeln!(&mut output, "{}{}", (8u8 as char), msg)
+	Self::unicode_or_ascii()
     }
 }
 
@@ -143,86 +531,328 @@ impl Default for Spin
 {
     fn default() -> Self
     {
-	Self {
-	    title: String::new(),
-	    chars: wheel::Wheel::default().into_iter(),
-	    current: '|',
-	    output: AtomicRefCell::new(create_default_output_device())
+	Self::new_default(Default::default())
+    }
+}
+
+/// `title`, truncated so the spinner's line fits in a terminal `terminal_width` columns wide, if that is known.
+fn fit_title(title: &str, frame_width: usize, terminal_width: Option<usize>) -> Cow<'_, str>
+{
+    let room = match terminal_width {
+	Some(tw) => tw.saturating_sub(1 + frame_width),
+	None => return Cow::Borrowed(title),
+    };
+    if width::width(title) <= room {
+	return Cow::Borrowed(title);
+    }
+    let mut fitted = String::new();
+    width::truncate_into(&mut fitted, title, room);
+    Cow::Owned(fitted)
+}
+
+/// Draw the spinner's line over the current line in `out`, with `frame` padded to `frame_width` columns.
+fn draw_line<W: ?Sized + io::Write>(out: &mut W, title: &str, frame: &str, frame_width: usize) -> io::Result<()>
+{
+    let pad = frame_width.saturating_sub(width::width(frame));
+    write!(out, "\r{} {}{:pad$}", title, frame, "")
+	.and_then(move |_| flush!(? out))
+}
+
+/// Blank a spinner line `line_width` columns wide in `out`.
+fn draw_blank<W: ?Sized + io::Write>(out: &mut W, line_width: usize) -> io::Result<()>
+{
+    out.write_all(b"\r")
+	.and_then(|_|
+		  stackalloc::stackalloc(line_width, b' ',
+					 |spaces| out.write_all(spaces)))
+	.and_then(|_| out.write_all(b"\r"))
+	.and_then(move |_| flush!(? out))
+}
+
+/// Show the cursor again in `out` if `show` is set, once the spinner's line has been finished.
+fn draw_cursor_shown<W: ?Sized + io::Write>(out: &mut W, show: bool) -> io::Result<()>
+{
+    if !show {
+	return Ok(());
+    }
+    out.write_all(cleanup::SHOW_CURSOR.as_bytes())
+	.and_then(move |_| flush!(? out))
+}
+
+/// Replace the spinner's frame, `frame_width` columns wide at the end of the line in `out`, with `msg` and end the line.
+fn draw_complete<W: ?Sized + io::Write>(out: &mut W, frame_width: usize, msg: &str) -> io::Result<()>
+{
+    let pad = frame_width.saturating_sub(width::width(msg));
+    stackalloc::stackalloc(frame_width, 8u8, |backspaces| out.write_all(backspaces))
+	.and_then(|_| writeln!(out, "{}{:pad$}", msg, ""))
+}
+
+impl<T: ?Sized> Spin<T>
+{
+    /// The current frame of the wheel.
+    #[inline]
+    fn current(&self) -> &str
+    {
+	self.wheel.frame(self.frame.load(Ordering::Relaxed))
+    }
+
+    /// The title as it is drawn, see `fit_title()`.
+    #[inline]
+    fn fitted_title(&self) -> Cow<'_, str>
+    {
+	fit_title(&self.title, self.frame_width, self.size.width())
+    }
+
+    /// The number of columns the spinner's line takes up.
+    #[inline]
+    fn line_width(&self) -> usize
+    {
+	width::width(&self.fitted_title()) + 1 + self.frame_width
+    }
+
+    /// Advance to the next spinner character.
+    #[inline]
+    fn advance(&self)
+    {
+	self.frame.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Remember that the spinner's line has been drawn, so it is finished when dropped.
+    ///
+    /// Returns what to write before the line, which hides the cursor the first time if enabled.
+    #[inline]
+    fn mark_drawn(&self) -> &'static str
+    {
+	self.drawn.store(true, Ordering::Relaxed);
+	if let Some(registration) = &self.registration {
+	    registration.activate();
+	}
+	if self.cursor.hide() { cleanup::HIDE_CURSOR } else { "" }
+    }
+
+    /// The spinner's line is being finished, so it needs no finishing when dropped, nor blanking by the panic hook.
+    ///
+    /// Returns whether the cursor should be shown again afterwards, see `draw_cursor_shown()`.
+    #[inline]
+    fn settle(&mut self) -> bool
+    {
+	self.finished = true;
+	self.registration = None;
+	self.cursor.show()
+    }
+}
+
+impl<T: ?Sized + io::Write> Spin<T>
+{
+    /// Finish the spinner's line according to its `progress::FinishMode`.
+    fn finish_with_mode(&mut self) -> io::Result<()>
+    {
+	use progress::FinishMode;
+	let show = self.settle();
+	let width = self.line_width();
+	let output = self.output.get_mut();
+	match &self.finish_mode {
+	    FinishMode::Leave | FinishMode::Fill => draw_complete(output, self.frame_width, ""),
+	    FinishMode::Clear => draw_blank(output, width),
+	    FinishMode::Replace(msg) => draw_blank(output, width)
+		.and_then(|_| writeln!(output, "{}", msg)),
+	    FinishMode::Abandon => draw_blank(output, width)
+		.and_then(|_| writeln!(output, "{}{}", progress::ABANDONED_MARKER, self.title)),
+	}?;
+	draw_cursor_shown(output, show)
+    }
+
+    /// Finish the spinner when it is dropped, if anything has been drawn and the panic hook has not already blanked it.
+    fn finish_on_drop(&mut self)
+    {
+	let cleaned = self.registration.as_ref().is_some_and(cleanup::Registration::is_cleaned);
+	if self.finished || cleaned || !*self.drawn.get_mut() || self.errors.is_disabled() {
+	    return;
+	}
+	let res = self.finish_with_mode();
+	self.errors.handle(res);
+    }
+}
+
+impl<T: ?Sized> Drop for Spin<T>
+{
+    fn drop(&mut self)
+    {
+	if let Some(finish) = self.finish_on_drop.take() {
+	    finish(self);
+	    // SAFETY: `into_inner()` has not taken `output`, since it takes `finish_on_drop` when it does.
+	    unsafe { ManuallyDrop::drop(&mut self.output) }
+	}
+    }
+}
+
+impl<T: ?Sized + io::Write> Spin<T>
+{
+    /// Refresh the display, returning any I/O error that occurs.
+    ///
+    /// If another thread is currently engaging in a display operation, nothing is written and `Ok(())` is returned.
+    pub fn try_refresh(&self) -> io::Result<()>
+    {
+	if self.errors.is_disabled() {
+	    return Ok(());
+	}
+	let Ok(mut output) = self.output.try_borrow_mut() else { return Ok(()) };
+	output.write_all(self.mark_drawn().as_bytes())?;
+	draw_line(&mut *output, &self.fitted_title(), self.current(), self.frame_width)
+    }
+
+    /// Blank the display, returning any I/O error that occurs.
+    ///
+    /// If another thread is currently engaging in a display operation, nothing is written and `Ok(())` is returned.
+    pub fn try_blank(&self) -> io::Result<()>
+    {
+	if self.errors.is_disabled() {
+	    return Ok(());
+	}
+	let Ok(mut output) = self.output.try_borrow_mut() else { return Ok(()) };
+	output.write_all(self.mark_drawn().as_bytes())?;
+	draw_blank(&mut *output, self.line_width())
+    }
+
+    /// Set the title and redisplay, returning any I/O error that occurs.
+    pub fn try_set_title(&mut self, from: &str) -> io::Result<()>
+    {
+	let size = self.line_width();
+	self.title = from.to_string();
+	if self.errors.is_disabled() {
+	    return Ok(());
+	}
+
+	let hide = self.mark_drawn();
+	let title = fit_title(&self.title, self.frame_width, self.size.width());
+	let output = self.output.get_mut();
+	output.write_all(hide.as_bytes())?;
+	draw_blank(output, size)?;
+	draw_line(output, &title, self.wheel.frame(*self.frame.get_mut()), self.frame_width)
+    }
+
+    /// Blank then print a line, and redisplay, returning any I/O error that occurs.
+    ///
+    /// If another thread is currently engaging in a display operation, nothing is written and `Ok(())` is returned.
+    pub fn try_println(&self, string: &str) -> io::Result<()>
+    {
+	if self.errors.is_disabled() {
+	    return Ok(());
+	}
+	let mut output = loop {
+	    match self.output.try_borrow_mut() {
+		Ok(output) => break output,
+		// A tick only holds the output for as long as it takes to draw a frame, so wait for it rather than lose the line.
+		Err(_) if self.ticking.load(Ordering::Acquire) => thread::yield_now(),
+		// The tick may have finished just after we tried.
+		Err(_) => match self.output.try_borrow_mut() {
+		    Ok(output) => break output,
+		    Err(_) => return Ok(()),
+		},
+	    }
+	};
+	output.write_all(self.mark_drawn().as_bytes())?;
+	draw_blank(&mut *output, self.line_width())?;
+	writeln!(output, "{}", string)?;
+	draw_line(&mut *output, &self.fitted_title(), self.current(), self.frame_width)
+    }
+
+    /// Cause the spinner to increment once, returning any I/O error that occurs.
+    pub fn try_bump(&mut self) -> io::Result<()>
+    {
+	self.advance();
+	if self.errors.is_disabled() {
+	    return Ok(());
 	}
+	let hide = self.mark_drawn();
+	let title = fit_title(&self.title, self.frame_width, self.size.width());
+	let output = self.output.get_mut();
+	output.write_all(hide.as_bytes())?;
+	draw_line(output, &title, self.wheel.frame(*self.frame.get_mut()), self.frame_width)
+    }
+
+    /// Cause the spinner to increment once through a shared reference, returning any I/O error that occurs.
+    ///
+    /// The spinner always advances, but if another thread is currently engaging in a display operation, nothing is written and `Ok(())` is returned.
+    pub fn try_tick(&self) -> io::Result<()>
+    {
+	self.advance();
+	if self.errors.is_disabled() {
+	    return Ok(());
+	}
+	self.ticking.store(true, Ordering::Release);
+	let res = match self.output.try_borrow_mut() {
+	    Ok(mut output) => {
+		output.write_all(self.mark_drawn().as_bytes())
+		    .and_then(|_| draw_line(&mut *output, &self.fitted_title(), self.current(), self.frame_width))
+	    },
+	    Err(_) => Ok(()),
+	};
+	self.ticking.store(false, Ordering::Release);
+	res
+    }
+
+    /// Cause the spinner to increment once through a shared reference.
+    ///
+    /// See `try_tick()`.
+    #[inline]
+    pub fn tick(&self)
+    {
+	self.errors.handle(self.try_tick());
+    }
+}
+
+impl<T: ?Sized + io::Write + Send + Sync + 'static> Spin<T>
+{
+    /// Tick the spinner every `interval` on a background thread, until the returned `SteadyTick` is dropped.
+    ///
+    /// Lines printed with `println()` in the meantime are never lost to a tick drawing at the same time.
+    /// The thread holds a reference to the spinner, so it cannot be unwrapped from its `Arc` (e.g. to complete it) until the `SteadyTick` is dropped.
+    pub fn steady_tick(self: &Arc<Self>, interval: Duration) -> tick::SteadyTick
+    {
+	let this = Arc::clone(self);
+	tick::SteadyTick::spawn(interval, move || this.tick(), || ())
     }
 }
 
 impl<T: ?Sized + io::Write> Display for Spin<T>
 {
+    #[inline]
     fn refresh(&self)
     {
-	let Ok(mut output) = self.output.try_borrow_mut() else { return };
-	
-	//TODO: What to do about I/O errors?
-	let _ = write!(&mut output, "\r{} {}", self.title, self.current)
-	    .and_then(move |_| flush!(? output));
+	self.errors.handle(self.try_refresh());
     }
+    #[inline]
     fn blank(&self)
     {
-	let Ok(mut output) = self.output.try_borrow_mut() else { return };
-	
-	//TODO: What to do about I/O errors?
-	let _ = output.write_all(b"\r")
-	    .and_then(|_|
-		      stackalloc::stackalloc(self.title.chars().count(), b' ',
-					     |spaces| output.write_all(spaces)))
-	    .and_then(|_| write!(&mut output, "  \r"))
-	    .and_then(move |_| flush!(? output));
+	self.errors.handle(self.try_blank());
     }
     fn get_title(&self) -> &str
     {
 	&self.title[..]
     }
+    #[inline]
     fn set_title(&mut self, from: &str)
     {
-	//self.blank(), with exclusive access
-	let mut output = self.output.get_mut();
-
-	let size = self.title.chars().count();
-	let _ = output.write_all(b"\r")
-	    .and_then(|_|
-		      stackalloc::stackalloc(size, b' ',
-					     |spaces| output.write_all(spaces)))
-	    .and_then(|_| write!(&mut output, "  \r"))
-	    .and_then(|_| flush!(? output));
-	
-	self.title = from.to_string();
-	
-	//self.refresh(), with exclusive access
-	let _ = write!(&mut output, "\r{} {}", self.title, self.current)
-	    .and_then(move |_| flush!(? output));
+	let res = self.try_set_title(from);
+	self.errors.handle(res);
     }
     fn update_dimensions(&mut self, _:usize){}
 
+    #[inline]
     fn println(&self, string: &str)
     {
-	self.blank();
-	if let Ok(mut output) = self.output.try_borrow_mut() {
-	    
-	    //TODO: What to do about I/O errors?
-	    let _ = writeln!(&mut output, "{}", string);
-	    drop(output)
-	} else {
-	    return;
-	}
-	self.refresh();
+	self.errors.handle(self.try_println(string));
     }
 }
 
 impl<T: ?Sized + io::Write> Spinner for Spin<T>
 {
+    #[inline]
     fn bump(&mut self)
     {
-	self.current = self.chars.next().unwrap();
-	let mut output = self.output.get_mut();
-	
-	let _ = write!(&mut output, "\r{} {}", self.title, self.current)
-	    .and_then(move |_| flush!(? output));
+	let res = self.try_bump();
+	self.errors.handle(res);
     }
 }
 
@@ -230,12 +860,10 @@ impl<T: ?Sized + io::Write> Spinner for Spin<T>
 impl<T: io::Write> WithTitle for Spin<T>
 {
     #[inline] 
-    fn with_title(self, t: impl AsRef<str>) -> Self
+    fn with_title(mut self, t: impl AsRef<str>) -> Self
     {
-	Self {
-	    title: t.as_ref().to_owned(),
-	    ..self
-	}
+	self.title = t.as_ref().to_owned();
+	self
     }
     #[inline] 
     fn add_title(&mut self, t: impl AsRef<str>)
@@ -244,9 +872,161 @@ impl<T: io::Write> WithTitle for Spin<T>
 	//	Self::with_title(t.as_ref(), Default::default())
     }
     #[inline] fn update(&mut self){}
-    #[inline] fn complete(self)
+    #[inline] fn complete(mut self)
     {
-	//TODO: What to do about I/O errors?
-	let _ = Spin::complete(self);
+	let show = self.settle();
+	if self.errors.is_disabled() {
+	    return;
+	}
+	let output = self.output.get_mut();
+	let res = draw_complete(output, self.frame_width, "")
+	    .and_then(|_| draw_cursor_shown(output, show));
+	self.errors.handle(res);
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    #[test]
+    fn spinning()
+    {
+	let term = testing::VirtualTerminal::new(20, 2);
+	let mut spin = Spin::with_title(term.clone(), "loading", Default::default());
+	spin.refresh();
+	assert_eq!(term.screen(), ["loading -", ""]);
+	spin.bump();
+	assert_eq!(term.screen(), ["loading \\", ""]);
+	spin.set_title("done");
+	assert_eq!(term.screen(), ["done \\", ""]);
+	spin.complete_with("ok").unwrap();
+	assert_eq!(term.screen(), ["done ok", ""]);
+	assert_eq!(term.cursor(), (1, 0));
+    }
+
+    #[test]
+    fn truncating_titles()
+    {
+	let term = testing::VirtualTerminal::new(12, 2);
+	let mut spin = Spin::with_size(term.clone(), "日本語のタイトルabc", Default::default(), term.clone());
+	spin.refresh();
+	assert_eq!(term.screen(), ["日本語... -", ""]);
+	spin.bump();
+	assert_eq!(term.screen(), ["日本語... \\", ""]);
+	spin.set_title("ab🎉cd🎉ef🎉gh");
+	assert_eq!(term.screen(), ["ab🎉cd... \\", ""]);
+	spin.println("printed");
+	assert_eq!(term.screen(), ["printed", "ab🎉cd... \\"]);
+	spin.set_title("short");
+	assert_eq!(term.screen(), ["printed", "short \\"]);
+	assert!(term.scrollback().is_empty(), "the line never wrapped");
+    }
+
+    #[test]
+    fn wide_frames()
+    {
+	let term = testing::VirtualTerminal::new(20, 2);
+	let mut spin = Spin::with_title(term.clone(), "syncing", wheel::Wheel::new(["..", "🕐", "."]));
+	spin.refresh();
+	assert_eq!(term.screen(), ["syncing 🕐", ""]);
+	spin.bump();
+	assert_eq!(term.screen(), ["syncing .", ""]);
+	spin.set_title("sync");
+	assert_eq!(term.screen(), ["sync .", ""]);
+	spin.complete_with("ok").unwrap();
+	assert_eq!(term.screen(), ["sync ok", ""]);
+    }
+
+    #[test]
+    fn finishing()
+    {
+	let term = testing::VirtualTerminal::new(20, 2);
+	let spin = Spin::with_title(term.clone(), "a long title", Default::default())
+	    .with_marks(Marks::ASCII);
+	spin.refresh();
+	spin.succeed("ok").unwrap();
+	assert_eq!(term.screen(), ["+ ok", ""]);
+
+	let mut out = Vec::new();
+	let spin = Spin::with_title(&mut out, "deploying", Default::default())
+	    .with_marks(Marks::UNICODE)
+	    .with_color_level(color::ColorLevel::Ansi16);
+	spin.fail("").unwrap();
+	assert!(String::from_utf8(out).unwrap().ends_with("\r\x1B[31m✖\x1B[0m deploying\n"));
+    }
+
+    #[test]
+    fn disabled_on_broken_pipe()
+    {
+	let spin = || {
+	    let (reader, writer) = io::pipe().unwrap();
+	    drop(reader);
+	    let mut spin = Spin::new(writer, Default::default())
+		.with_error_policy(ErrorPolicy::DisableOnBrokenPipe);
+	    spin.bump();
+	    assert!(spin.is_disabled());
+	    spin
+	};
+	spin().complete().unwrap();
+	spin().complete_with("done").unwrap();
+	spin().succeed("done").unwrap();
+    }
+
+    #[test]
+    fn dropping()
+    {
+	let term = testing::VirtualTerminal::new(20, 4);
+	let spin = Spin::with_title(term.clone(), "loading", Default::default());
+	spin.refresh();
+	drop(spin);
+	assert_eq!(term.screen(), ["loading", "", "", ""]);
+	assert_eq!(term.cursor(), (1, 0));
+
+	let spin = Spin::with_title(term.clone(), "syncing", Default::default())
+	    .with_finish_mode(progress::FinishMode::Abandon);
+	spin.refresh();
+	drop(spin);
+	assert_eq!(term.screen(), ["loading", "(abandoned) syncing", "", ""]);
+
+	drop(Spin::with_title(term.clone(), "never drawn", Default::default()));
+	let spin = Spin::with_title(term.clone(), "taken", Default::default());
+	spin.refresh();
+	let _ = spin.into_inner();
+	assert_eq!(term.screen(), ["loading", "(abandoned) syncing", "taken -", ""]);
+    }
+
+    #[test]
+    fn hiding_cursor()
+    {
+	let term = testing::VirtualTerminal::new(20, 2);
+	let mut spin = Spin::with_title(term.clone(), "loading", Default::default())
+	    .with_hide_cursor(true);
+	spin.bump();
+	assert!(!term.cursor_visible());
+	spin.complete_with("ok").unwrap();
+	assert!(term.cursor_visible());
+    }
+
+    #[test]
+    fn ticking_in_background()
+    {
+	let term = testing::VirtualTerminal::new(20, 40);
+	let spin = Arc::new(Spin::with_title(term.clone(), "waiting", Default::default()));
+	let ticker = spin.steady_tick(Duration::from_millis(1));
+	for i in 0..20 {
+	    spin.println(&format!("line {i}"));
+	}
+	while spin.frame.load(Ordering::Relaxed) < 5 {
+	    thread::yield_now();
+	}
+	ticker.stop();
+	let screen = term.screen();
+	for (i, line) in screen[..20].iter().enumerate() {
+	    assert_eq!(*line, format!("line {i}"));
+	}
+	assert!(screen[20].starts_with("waiting "), "{screen:?}");
+	Arc::try_unwrap(spin).unwrap().complete().unwrap();
     }
 }
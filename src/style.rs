@@ -0,0 +1,151 @@
+//! The characters a `progress::Bar` is drawn with.
+
+/// The partially filled block glyphs, from 1/8th to 7/8ths filled.
+const EIGHTHS: [char; 7] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+/// The characters a `progress::Bar` is drawn with.
+///
+/// # Styles
+/// * `BarStyle::DEFAULT`: `[=========================                         ]`
+/// * `BarStyle::ASCII`: `[========================>                         ]`, for terminals that cannot display Unicode.
+/// * `BarStyle::SMOOTH`: `│████████████████████████▌                         │`, which uses the eighth-block glyphs to render 8 distinct steps per cell.
+///
+/// `BarStyle::unicode_or_ascii()` picks `SMOOTH` or `ASCII` depending on whether the locale supports UTF-8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BarStyle
+{
+    /// The character before the bar, if any.
+    pub open: Option<char>,
+    /// The character after the bar, if any.
+    pub close: Option<char>,
+    /// The character for filled cells.
+    pub filled: char,
+    /// The character for empty cells.
+    pub empty: char,
+    /// The character for the first empty cell after the filled ones, if any.
+    ///
+    /// Ignored when `smooth` is set.
+    pub head: Option<char>,
+    /// Render the partially filled cell at the end of the filled ones with the eighth-block glyphs (`▏▎▍▌▋▊▉`).
+    pub smooth: bool,
+}
+
+/// Does the locale's charset support UTF-8?
+pub(crate) fn utf8_locale() -> bool
+{
+    // The first of these that is set decides the locale's charset.
+    ["LC_ALL", "LC_CTYPE", "LANG"].into_iter()
+	.filter_map(std::env::var_os)
+	.find(|v| !v.is_empty())
+	.map(|locale| locale.to_string_lossy().to_ascii_lowercase())
+	.is_some_and(|locale| locale.contains("utf-8") || locale.contains("utf8"))
+}
+
+impl BarStyle
+{
+    /// The default style: `[=====     ]`.
+    pub const DEFAULT: Self = Self {
+	open: Some('['),
+	close: Some(']'),
+	filled: '=',
+	empty: ' ',
+	head: None,
+	smooth: false,
+    };
+
+    /// An ASCII-only style for terminals that cannot display Unicode: `[====>     ]`.
+    pub const ASCII: Self = Self {
+	head: Some('>'),
+	..Self::DEFAULT
+    };
+
+    /// A smooth Unicode style with 8 distinct steps per cell: `│████▌     │`.
+    pub const SMOOTH: Self = Self {
+	open: Some('│'),
+	close: Some('│'),
+	filled: '█',
+	empty: ' ',
+	head: None,
+	smooth: true,
+    };
+
+    /// `SMOOTH` if the locale supports UTF-8 (according to `LC_ALL`, `LC_CTYPE` or `LANG`), `ASCII` otherwise.
+    pub fn unicode_or_ascii() -> Self
+    {
+	if utf8_locale() { Self::SMOOTH } else { Self::ASCII }
+    }
+
+    /// Write a bar `width` cells wide at `progress` (in `0..=1`) into `out`, without the brackets.
+    ///
+    /// Returns the length in bytes of the filled part (including any partial or head cell) written.
+    pub(crate) fn render_into(&self, out: &mut String, width: usize, progress: f64) -> usize
+    {
+	let start = out.len();
+	let progress = if progress.is_nan() { 0.0 } else { progress.clamp(0.0, 1.0) };
+	let mut cells = 0;
+	if self.smooth {
+	    let eighths = (progress * (width * 8) as f64) as usize;
+	    let (full, partial) = (eighths / 8, eighths % 8);
+	    out.extend(std::iter::repeat_n(self.filled, full));
+	    cells += full;
+	    if partial > 0 && cells < width {
+		out.push(EIGHTHS[partial - 1]);
+		cells += 1;
+	    }
+	} else {
+	    let full = ((progress * width as f64) as usize).min(width);
+	    out.extend(std::iter::repeat_n(self.filled, full));
+	    cells += full;
+	    if let Some(head) = self.head && cells < width {
+		out.push(head);
+		cells += 1;
+	    }
+	}
+	let filled = out.len() - start;
+	out.extend(std::iter::repeat_n(self.empty, width.saturating_sub(cells)));
+	filled
+    }
+}
+
+impl Default for BarStyle
+{
+    #[inline]
+    fn default() -> Self
+    {
+	Self::DEFAULT
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    fn render(style: BarStyle, width: usize, progress: f64) -> String
+    {
+	let mut out = String::new();
+	style.render_into(&mut out, width, progress);
+	assert_eq!(out.chars().count(), width);
+	out
+    }
+
+    #[test]
+    fn styles()
+    {
+	assert_eq!(render(BarStyle::DEFAULT, 10, 0.55), "=====     ");
+	assert_eq!(render(BarStyle::ASCII, 10, 0.55), "=====>    ");
+	assert_eq!(render(BarStyle::ASCII, 10, 1.0), "==========");
+	assert_eq!(render(BarStyle::SMOOTH, 10, 0.55), "█████▌    ");
+	assert_eq!(render(BarStyle::SMOOTH, 10, 1.0), "██████████");
+	assert_eq!(render(BarStyle::SMOOTH, 10, 0.0), "          ");
+    }
+
+    #[test]
+    fn smooth_steps()
+    {
+	let steps: std::collections::HashSet<_> = (0..=1000)
+	    .map(|i| render(BarStyle::SMOOTH, 50, i as f64 / 1000.0))
+	    .collect();
+	assert_eq!(steps.len(), 401);
+    }
+}
@@ -0,0 +1,415 @@
+//! Templates for the layout of a `progress::Bar`'s line.
+//!
+//! A template is a string containing placeholders in braces, e.g. `"{spinner} {title:20} [{bar}] {pos}/{len} {eta}"`, which is parsed once into a `Template`.
+//!
+//! # Placeholders
+//! | Placeholder | Renders |
+//! |-------------|---------|
+//! | `{bar}`     | The bar itself (without brackets) |
+//! | `{bracketed_bar}` | The bar between the brackets of its `BarStyle` (e.g. `[===  ]`, or `│██▌  │` for `BarStyle::SMOOTH`) |
+//! | `{pct}`     | The percentage, with 2 decimal places unless a precision is given (e.g. `{pct:.0}`) |
+//! | `{title}`   | The title. Without a width, it takes up the rest of the line and is truncated to fit |
+//! | `{pos}`     | The position (see `Bar::set_length()`) |
+//! | `{len}`     | The length, or `?` if the bar has none |
+//! | `{rate}`    | The rate the position is advancing at, e.g. `450 it/s` |
+//! | `{elapsed}` | The time elapsed since the bar was created |
+//! | `{eta}`     | The estimated time remaining, or `--:--` if there is no estimate yet |
+//! | `{spinner}` | A spinner frame that advances every time the bar is updated (see `Bar::with_spinner()`) |
+//! | `{stats}`   | The segments enabled on the bar with `with_rate()`, `with_elapsed()` and `with_eta()` (and the position and length, if it has a length), each preceded by a space |
+//!
+//! # Width and alignment
+//! A placeholder can be given a minimum width after a `:`, optionally preceded by an alignment: `<` (left), `>` (right) or `^` (centre), e.g. `{pos:>8}`.
+//! Numeric placeholders are aligned right by default, and all others left.
+//! A `{title}` with a width is also truncated to it.
+//!
+//! Literal braces are written as `{{` and `}}`.
+
+use super::width;
+use std::{
+    error,
+    fmt,
+    str::FromStr,
+};
+
+/// The template a `progress::Bar` uses unless given another one.
+pub const DEFAULT_TEMPLATE: &str = "{bracketed_bar}: {pct:.2}%{stats} {title}";
+
+/// A value that can be rendered in a template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Placeholder
+{
+    Bar,
+    BracketedBar,
+    Percent,
+    Title,
+    Position,
+    Length,
+    Rate,
+    Elapsed,
+    Eta,
+    Spinner,
+    Stats,
+}
+
+impl Placeholder
+{
+    /// The name of this placeholder in a template string.
+    pub const fn name(self) -> &'static str
+    {
+	match self {
+	    Self::Bar => "bar",
+	    Self::BracketedBar => "bracketed_bar",
+	    Self::Percent => "pct",
+	    Self::Title => "title",
+	    Self::Position => "pos",
+	    Self::Length => "len",
+	    Self::Rate => "rate",
+	    Self::Elapsed => "elapsed",
+	    Self::Eta => "eta",
+	    Self::Spinner => "spinner",
+	    Self::Stats => "stats",
+	}
+    }
+
+    const ALL: [Self; 11] = [Self::Bar, Self::BracketedBar, Self::Percent, Self::Title, Self::Position, Self::Length, Self::Rate, Self::Elapsed, Self::Eta, Self::Spinner, Self::Stats];
+
+    fn from_name(name: &str) -> Option<Self>
+    {
+	Self::ALL.into_iter().find(|p| p.name() == name)
+    }
+
+    #[inline]
+    const fn default_align(self) -> Align
+    {
+	match self {
+	    Self::Percent | Self::Position | Self::Length | Self::Rate => Align::Right,
+	    _ => Align::Left,
+	}
+    }
+}
+
+impl fmt::Display for Placeholder
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+	f.write_str(self.name())
+    }
+}
+
+/// The alignment of a placeholder's value within its width.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Align
+{
+    Left,
+    Right,
+    Centre,
+}
+
+/// The format specifier of a placeholder (the part after `:`.)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Spec
+{
+    /// The alignment, if one was given.
+    pub align: Option<Align>,
+    /// The minimum width, if one was given.
+    pub width: Option<usize>,
+    /// The precision, if one was given.
+    pub precision: Option<usize>,
+}
+
+impl Spec
+{
+    fn parse(spec: &str) -> Option<Self>
+    {
+	let (align, rest) = match spec.as_bytes().first() {
+	    Some(b'<') => (Some(Align::Left), &spec[1..]),
+	    Some(b'>') => (Some(Align::Right), &spec[1..]),
+	    Some(b'^') => (Some(Align::Centre), &spec[1..]),
+	    _ => (None, spec),
+	};
+	let (width, precision) = match rest.split_once('.') {
+	    Some((width, precision)) => (width, Some(precision.parse().ok()?)),
+	    None => (rest, None),
+	};
+	let width = if width.is_empty() {
+	    None
+	} else {
+	    Some(width.parse().ok()?)
+	};
+	Some(Self { align, width, precision })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment
+{
+    Literal(String),
+    Placeholder(Placeholder, Spec),
+}
+
+/// An error parsing a template string.
+///
+/// Positions are byte offsets into the template string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TemplateError
+{
+    /// A `{` was never closed.
+    Unclosed(usize),
+    /// A `}` was not part of a placeholder, and not escaped as `}}`.
+    UnmatchedClose(usize),
+    /// A placeholder's name is not one of the known placeholders.
+    UnknownPlaceholder(usize, String),
+    /// A placeholder's format specifier could not be parsed.
+    InvalidSpec(usize, String),
+    /// `{title}` appears more than once without a width.
+    MultipleTitles(usize),
+}
+
+impl error::Error for TemplateError{}
+
+impl fmt::Display for TemplateError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+	match self {
+	    Self::Unclosed(at) => write!(f, "unclosed `{{` at {at}"),
+	    Self::UnmatchedClose(at) => write!(f, "unmatched `}}` at {at} (use `}}}}` for a literal brace)"),
+	    Self::UnknownPlaceholder(at, name) => write!(f, "unknown placeholder {name:?} at {at}"),
+	    Self::InvalidSpec(at, spec) => write!(f, "invalid format specifier {spec:?} at {at}"),
+	    Self::MultipleTitles(at) => write!(f, "second `{{title}}` without a width at {at}"),
+	}
+    }
+}
+
+/// A parsed template for the layout of a `progress::Bar`'s line.
+///
+/// See the module documentation for the syntax.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template
+{
+    source: String,
+    segments: Vec<Segment>,
+}
+
+impl Template
+{
+    /// Parse a template string.
+    pub fn parse(source: &str) -> Result<Self, TemplateError>
+    {
+	let mut segments = Vec::new();
+	let mut literal = String::new();
+	let mut title = false;
+	let mut chars = source.char_indices().peekable();
+	while let Some((at, ch)) = chars.next() {
+	    match ch {
+		'{' if chars.next_if(|&(_, c)| c == '{').is_some() => literal.push('{'),
+		'}' if chars.next_if(|&(_, c)| c == '}').is_some() => literal.push('}'),
+		'}' => return Err(TemplateError::UnmatchedClose(at)),
+		'{' => {
+		    let end = source[at..].find('}').ok_or(TemplateError::Unclosed(at))? + at;
+		    let inner = &source[(at + 1)..end];
+		    let (name, spec) = inner.split_once(':').unwrap_or((inner, ""));
+		    let placeholder = Placeholder::from_name(name.trim())
+			.ok_or_else(|| TemplateError::UnknownPlaceholder(at, name.to_owned()))?;
+		    let spec = Spec::parse(spec)
+			.ok_or_else(|| TemplateError::InvalidSpec(at, spec.to_owned()))?;
+		    if placeholder == Placeholder::Title && spec.width.is_none() {
+			if title {
+			    return Err(TemplateError::MultipleTitles(at));
+			}
+			title = true;
+		    }
+
+		    if !literal.is_empty() {
+			segments.push(Segment::Literal(std::mem::take(&mut literal)));
+		    }
+		    segments.push(Segment::Placeholder(placeholder, spec));
+		    while chars.next_if(|&(i, _)| i <= end).is_some() {}
+		},
+		ch => literal.push(ch),
+	    }
+	}
+	if !literal.is_empty() {
+	    segments.push(Segment::Literal(literal));
+	}
+	Ok(Self {
+	    source: source.to_owned(),
+	    segments,
+	})
+    }
+
+    /// The template string this was parsed from.
+    #[inline]
+    pub fn as_str(&self) -> &str
+    {
+	&self.source
+    }
+
+    /// Does this template contain `placeholder`?
+    #[inline]
+    pub fn contains(&self, placeholder: Placeholder) -> bool
+    {
+	self.segments.iter().any(|s| matches!(s, Segment::Placeholder(p, _) if *p == placeholder))
+    }
+
+    /// Render the template into `out`, fitting it into `max_width` columns.
+    ///
+    /// `value` is called to write the value of each placeholder other than `{title}`, which is rendered from `title`.
+    /// `paint` is then called to write each placeholder's rendered text into the line (e.g. in a colour) before it is padded.
+    /// Escape sequences in either do not count towards the width.
+    ///
+    /// Each placeholder is rendered in the buffers of `scratch`, which can be reused between renders.
+    pub(crate) fn render_into<F, P>(&self, out: &mut String, scratch: &mut Scratch, max_width: usize, title: &str, mut value: F, mut paint: P)
+    where F: FnMut(Placeholder, &Spec, &mut String),
+	  P: FnMut(Placeholder, &str, &mut String)
+    {
+	let start = out.len();
+	// Where the unsized title goes, once we know how much space is left for it.
+	let mut title_at = None;
+	let Scratch { value: scratch, painted } = scratch;
+	for segment in self.segments.iter() {
+	    match segment {
+		Segment::Literal(literal) => out.push_str(literal),
+		Segment::Placeholder(Placeholder::Title, Spec { width: None, .. }) => title_at = Some(out.len()),
+		Segment::Placeholder(placeholder, spec) => {
+		    scratch.clear();
+		    if *placeholder == Placeholder::Title {
+			width::truncate_into(scratch, title, spec.width.unwrap_or(usize::MAX));
+		    } else {
+			value(*placeholder, spec, scratch);
+		    }
+		    painted.clear();
+		    paint(*placeholder, scratch, painted);
+		    pad_into(out, painted, spec.width.unwrap_or(0), spec.align.unwrap_or(placeholder.default_align()));
+		},
+	    }
+	}
+	if let Some(at) = title_at {
+	    let used = width::width(&out[start..]);
+	    scratch.clear();
+	    width::truncate_into(scratch, title, max_width.saturating_sub(used));
+	    painted.clear();
+	    paint(Placeholder::Title, scratch, painted);
+	    out.insert_str(at, painted);
+	}
+    }
+}
+
+/// Buffers a placeholder is rendered in before it is painted and padded into the line.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Scratch
+{
+    value: String,
+    painted: String,
+}
+
+/// Write `value` into `out` with a minimum `width`.
+fn pad_into(out: &mut String, value: &str, width: usize, align: Align)
+{
+    let padding = width.saturating_sub(width::width(value));
+    let (before, after) = match align {
+	Align::Left => (0, padding),
+	Align::Right => (padding, 0),
+	Align::Centre => (padding / 2, padding - padding / 2),
+    };
+    out.extend(std::iter::repeat_n(' ', before));
+    out.push_str(value);
+    out.extend(std::iter::repeat_n(' ', after));
+}
+
+impl Default for Template
+{
+    #[inline]
+    fn default() -> Self
+    {
+	Self::parse(DEFAULT_TEMPLATE).expect("default template is valid")
+    }
+}
+
+impl FromStr for Template
+{
+    type Err = TemplateError;
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+	Self::parse(s)
+    }
+}
+
+impl TryFrom<&str> for Template
+{
+    type Error = TemplateError;
+    #[inline]
+    fn try_from(from: &str) -> Result<Self, Self::Error>
+    {
+	Self::parse(from)
+    }
+}
+
+impl fmt::Display for Template
+{
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+	f.write_str(&self.source)
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    fn render(template: &str, max_width: usize, title: &str) -> String
+    {
+	let mut out = String::new();
+	Template::parse(template).unwrap().render_into(&mut out, &mut Scratch::default(), max_width, title, |p, spec, out| {
+	    match p {
+		Placeholder::Percent => out.push_str(&format!("{:.*}", spec.precision.unwrap_or(2), 50.0)),
+		Placeholder::Position => out.push('5'),
+		p => out.push_str(p.name()),
+	    }
+	}, |_, text, out| out.push_str(text));
+	out
+    }
+
+    #[test]
+    fn parsing()
+    {
+	assert_eq!(Template::parse("{nope}"), Err(TemplateError::UnknownPlaceholder(0, "nope".into())));
+	assert_eq!(Template::parse("ab {bar"), Err(TemplateError::Unclosed(3)));
+	assert_eq!(Template::parse("a}b"), Err(TemplateError::UnmatchedClose(1)));
+	assert_eq!(Template::parse("{pos:x5}"), Err(TemplateError::InvalidSpec(0, "x5".into())));
+	assert_eq!(Template::parse("{title} {title}"), Err(TemplateError::MultipleTitles(8)));
+	assert!(Template::parse("{title:10} {title}").is_ok());
+	assert!(Template::parse(DEFAULT_TEMPLATE).unwrap().contains(Placeholder::Stats));
+    }
+
+    #[test]
+    fn rendering()
+    {
+	assert_eq!(render("[{bar}]: {pct:.1}% {title}", 80, "hi"), "[bar]: 50.0% hi");
+	assert_eq!(render("{bracketed_bar} {title}", 80, "hi"), "bracketed_bar hi");
+	assert_eq!(render("{{{pos:>3}}} {pos:<3}|{pos:^4}|", 80, ""), "{  5} 5  | 5  |");
+	assert_eq!(render("{title:6}|{pos}", 80, "long title"), "lon...|5");
+	assert_eq!(render("{title:6}|{pos}", 80, "abc"), "abc   |5");
+	assert_eq!(render("{pct} {title} {pos}", 20, "a very long title"), "50.00 a very lo... 5");
+    }
+
+    #[test]
+    fn escapes_have_no_width()
+    {
+	let mut out = String::new();
+	Template::parse("{pos:>3}|{title}").unwrap().render_into(&mut out, &mut Scratch::default(), 10, "a long title", |_, _, out| out.push('5'), |p, text, out| {
+	    out.push_str("\x1B[1m");
+	    out.push_str(text);
+	    if p == Placeholder::Title {
+		out.push_str("\x1B[0m");
+	    }
+	});
+	assert_eq!(out, "  \x1B[1m5|\x1B[1ma l...\x1B[0m");
+    }
+}
@@ -0,0 +1,429 @@
+//! Support for testing what indicators draw.
+//!
+//! A `VirtualTerminal` is an in-memory terminal with a small VT100 interpreter. Indicators draw to it like any other output, and the resulting screen can then be checked:
+//! ```rust
+//! # use termprogress::{prelude::*, testing::VirtualTerminal};
+//! let term = VirtualTerminal::new(40, 5);
+//! let mut bar: Bar<VirtualTerminal> = Bar::with_size(term.clone(), 10, term.clone());
+//! bar.set_title("hello");
+//! bar.set_progress(0.5);
+//! assert_eq!(term.screen()[0], "[=====     ]: 50.00% hello");
+//! ```
+//!
+//! Requires feature `test-support`.
+
+use super::*;
+use std::{
+    fmt,
+    io,
+    mem,
+    sync::{
+	Arc,
+	Mutex,
+	MutexGuard,
+	PoisonError,
+    },
+};
+use unicode_width::UnicodeWidthChar;
+
+/// The state of a `VirtualTerminal`.
+#[derive(Debug, Clone)]
+struct Screen
+{
+    width: usize,
+    height: usize,
+    /// `height` rows of `width` cells each. A cell holds the character drawn in it (plus any combining marks), and the second cell of a wide character is empty.
+    rows: Vec<Vec<String>>,
+    /// Rows scrolled off the top of the screen.
+    scrollback: Vec<String>,
+    cursor: (usize, usize),
+    cursor_visible: bool,
+    /// Bytes of an incomplete character or escape sequence, kept until the rest is written.
+    pending: Vec<u8>,
+}
+
+/// The result of interpreting the start of the pending bytes.
+enum Token
+{
+    Char(char),
+    /// A `CSI` sequence: its private marker (e.g. `?`), parameters, and final byte.
+    Csi(Option<u8>, Vec<usize>, u8),
+    /// Bytes that are not understood, and are skipped.
+    Skip,
+    /// The rest has not been written yet.
+    Incomplete,
+}
+
+impl Screen
+{
+    fn new(width: usize, height: usize) -> Self
+    {
+	Self {
+	    width,
+	    height,
+	    rows: vec![Self::blank_row(width); height],
+	    scrollback: Vec::new(),
+	    cursor: (0, 0),
+	    cursor_visible: true,
+	    pending: Vec::new(),
+	}
+    }
+
+    #[inline]
+    fn blank_row(width: usize) -> Vec<String>
+    {
+	vec![" ".to_owned(); width]
+    }
+
+    fn render_row(row: &[String]) -> String
+    {
+	row.concat().trim_end().to_owned()
+    }
+
+    /// Interpret the first token in `bytes`, returning it and its length.
+    fn next_token(bytes: &[u8]) -> (Token, usize)
+    {
+	match bytes {
+	    [] => (Token::Incomplete, 0),
+	    [0x1B] => (Token::Incomplete, 0),
+	    [0x1B, b'[', rest @ ..] => {
+		let Some(end) = rest.iter().position(|b| (0x40..=0x7e).contains(b)) else { return (Token::Incomplete, 0) };
+		let (private, params) = match rest[..end].split_first() {
+		    Some((&p, params)) if matches!(p, b'?' | b'>' | b'<' | b'=') => (Some(p), params),
+		    _ => (None, &rest[..end]),
+		};
+		let params = params.split(|&b| b == b';')
+		    .map(|p| std::str::from_utf8(p).ok().and_then(|p| p.parse().ok()).unwrap_or(0))
+		    .collect();
+		(Token::Csi(private, params, rest[end]), 2 + end + 1)
+	    },
+	    [0x1B, _, ..] => (Token::Skip, 2),
+	    _ => {
+		let len = match bytes[0] {
+		    0x00..0x80 => 1,
+		    0xC0..0xE0 => 2,
+		    0xE0..0xF0 => 3,
+		    0xF0..0xF8 => 4,
+		    _ => return (Token::Skip, 1),
+		};
+		if bytes.len() < len {
+		    return (Token::Incomplete, 0);
+		}
+		match std::str::from_utf8(&bytes[..len]).ok().and_then(|s| s.chars().next()) {
+		    Some(c) => (Token::Char(c), len),
+		    None => (Token::Skip, 1),
+		}
+	    },
+	}
+    }
+
+    fn feed(&mut self, buf: &[u8])
+    {
+	let mut bytes = mem::take(&mut self.pending);
+	bytes.extend_from_slice(buf);
+	let mut at = 0;
+	while at < bytes.len() {
+	    let (token, len) = Self::next_token(&bytes[at..]);
+	    match token {
+		Token::Incomplete => break,
+		Token::Skip => (),
+		Token::Char(c) => self.put(c),
+		Token::Csi(private, params, command) => self.csi(private, &params, command),
+	    }
+	    at += len;
+	}
+	bytes.drain(..at);
+	self.pending = bytes;
+    }
+
+    /// Move the cursor down a line, scrolling if it is on the last one.
+    fn line_feed(&mut self)
+    {
+	if self.cursor.0 + 1 < self.height {
+	    self.cursor.0 += 1;
+	} else {
+	    let top = self.rows.remove(0);
+	    self.scrollback.push(Self::render_row(&top));
+	    self.rows.push(Self::blank_row(self.width));
+	}
+    }
+
+    fn put(&mut self, c: char)
+    {
+	match c {
+	    '\r' => self.cursor.1 = 0,
+	    // Like a terminal in cooked mode, where `\n` is output as `\r\n`.
+	    '\n' => {
+		self.cursor.1 = 0;
+		self.line_feed();
+	    },
+	    '\x08' => self.cursor.1 = self.cursor.1.saturating_sub(1),
+	    '\t' => self.cursor.1 = ((self.cursor.1 / 8 + 1) * 8).min(self.width.saturating_sub(1)),
+	    c if c.is_control() => (),
+	    c => {
+		let width = c.width().unwrap_or(0);
+		if width == 0 {
+		    // A combining mark joins the previous character.
+		    let (row, col) = self.cursor;
+		    if let Some(cell) = col.checked_sub(1).map(|col| &mut self.rows[row][col]) {
+			cell.push(c);
+		    }
+		    return;
+		}
+		if self.cursor.1 + width > self.width {
+		    self.cursor.1 = 0;
+		    self.line_feed();
+		}
+		let (row, col) = self.cursor;
+		self.rows[row][col] = c.to_string();
+		if width == 2 && col + 1 < self.width {
+		    self.rows[row][col + 1].clear();
+		}
+		self.cursor.1 += width;
+	    },
+	}
+    }
+
+    /// Clear the cells `from..to` of `row`.
+    fn erase(&mut self, row: usize, from: usize, to: usize)
+    {
+	for cell in &mut self.rows[row][from.min(self.width)..to.min(self.width)] {
+	    " ".clone_into(cell);
+	}
+    }
+
+    fn csi(&mut self, private: Option<u8>, params: &[usize], command: u8)
+    {
+	let n = params.first().copied().filter(|&n| n > 0).unwrap_or(1);
+	let (row, col) = self.cursor;
+	match (private, command) {
+	    (Some(b'?'), b'l' | b'h') if params.contains(&25) => self.cursor_visible = command == b'h',
+	    (Some(_), _) => (),
+	    (None, b'A') => self.cursor.0 = row.saturating_sub(n),
+	    (None, b'B') => self.cursor.0 = (row + n).min(self.height - 1),
+	    (None, b'C') => self.cursor.1 = (col + n).min(self.width),
+	    (None, b'D') => self.cursor.1 = col.min(self.width).saturating_sub(n),
+	    (None, b'G') => self.cursor.1 = (n - 1).min(self.width - 1),
+	    (None, b'H' | b'f') => {
+		let col = params.get(1).copied().filter(|&n| n > 0).unwrap_or(1);
+		self.cursor = ((n - 1).min(self.height - 1), (col - 1).min(self.width - 1));
+	    },
+	    (None, b'K') => match params.first().copied().unwrap_or(0) {
+		0 => self.erase(row, col, self.width),
+		1 => self.erase(row, 0, col + 1),
+		_ => self.erase(row, 0, self.width),
+	    },
+	    (None, b'J') => match params.first().copied().unwrap_or(0) {
+		0 => {
+		    self.erase(row, col, self.width);
+		    for row in (row + 1)..self.height {
+			self.erase(row, 0, self.width);
+		    }
+		},
+		1 => {
+		    for row in 0..row {
+			self.erase(row, 0, self.width);
+		    }
+		    self.erase(row, 0, col + 1);
+		},
+		_ => for row in 0..self.height {
+		    self.erase(row, 0, self.width);
+		},
+	    },
+	    // Styles (`m`) and anything else do not change the text on the screen.
+	    _ => (),
+	}
+    }
+}
+
+/// An in-memory terminal `width` columns wide and `height` rows high, which interprets what is written to it.
+///
+/// Clones share the same screen, so one can be given to an indicator as its output (and as its `size::SizeQuery`) while another is used to check what it drew.
+///
+/// # Interpreted
+/// * Printable characters, including wide characters and combining marks, wrapping at the end of the line and scrolling at the bottom of the screen.
+/// * `\r`, `\n` (as `\r\n`, like a terminal in cooked mode), backspace and tab.
+/// * Cursor movement: `ESC[nA`, `ESC[nB`, `ESC[nC`, `ESC[nD`, `ESC[nG` and `ESC[r;cH`.
+/// * Erasing: `ESC[K` and `ESC[J` (with any of their modes.)
+/// * Cursor visibility: `ESC[?25l` and `ESC[?25h`.
+///
+/// Styles (`ESC[...m`) and any other sequences are ignored.
+///
+/// Indicators drawing to it are always drawn in `lines::DrawMode::Terminal` unless set otherwise, even when the `CI` environment variable is set, so snapshots do not depend on where the tests run.
+#[derive(Clone)]
+pub struct VirtualTerminal
+{
+    screen: Arc<Mutex<Screen>>,
+}
+
+impl VirtualTerminal
+{
+    /// Create a new, blank, terminal `width` columns wide and `height` rows high.
+    ///
+    /// # Panics
+    /// If either is 0.
+    pub fn new(width: usize, height: usize) -> Self
+    {
+	assert!(width > 0 && height > 0, "terminal must have at least one cell");
+	Self {
+	    screen: Arc::new(Mutex::new(Screen::new(width, height))),
+	}
+    }
+
+    #[inline]
+    fn lock(&self) -> MutexGuard<'_, Screen>
+    {
+	self.screen.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The width in columns.
+    #[inline]
+    pub fn width(&self) -> usize
+    {
+	self.lock().width
+    }
+
+    /// Change the width. The screen is not redrawn, lines are cut or extended with blanks.
+    pub fn resize(&self, width: usize)
+    {
+	assert!(width > 0, "terminal must have at least one column");
+	let mut screen = self.lock();
+	screen.width = width;
+	for row in &mut screen.rows {
+	    row.resize(width, " ".to_owned());
+	}
+	screen.cursor.1 = screen.cursor.1.min(width);
+    }
+
+    /// The rows of the screen, with trailing blanks removed.
+    pub fn screen(&self) -> Vec<String>
+    {
+	self.lock().rows.iter().map(|row| Screen::render_row(row)).collect()
+    }
+
+    /// The rows scrolled off the top of the screen, oldest first.
+    pub fn scrollback(&self) -> Vec<String>
+    {
+	self.lock().scrollback.clone()
+    }
+
+    /// The scrollback and the screen as one string, a row per line, with trailing blank rows removed.
+    ///
+    /// Useful for snapshot tests.
+    pub fn contents(&self) -> String
+    {
+	let screen = self.lock();
+	let mut rows: Vec<_> = screen.scrollback.iter().cloned()
+	    .chain(screen.rows.iter().map(|row| Screen::render_row(row)))
+	    .collect();
+	while rows.last().is_some_and(|row| row.is_empty()) {
+	    rows.pop();
+	}
+	rows.join("\n")
+    }
+
+    /// The cursor's row and column, from 0.
+    #[inline]
+    pub fn cursor(&self) -> (usize, usize)
+    {
+	self.lock().cursor
+    }
+
+    /// Is the cursor shown?
+    #[inline]
+    pub fn cursor_visible(&self) -> bool
+    {
+	self.lock().cursor_visible
+    }
+}
+
+impl fmt::Debug for VirtualTerminal
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+	f.debug_struct("VirtualTerminal")
+	    .field("screen", &self.screen())
+	    .field("cursor", &self.cursor())
+	    .finish()
+    }
+}
+
+impl io::Write for VirtualTerminal
+{
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize>
+    {
+	self.lock().feed(buf);
+	Ok(buf.len())
+    }
+    #[inline]
+    fn flush(&mut self) -> io::Result<()>
+    {
+	Ok(())
+    }
+}
+
+impl size::SizeQuery for VirtualTerminal
+{
+    #[inline]
+    fn width(&self) -> Option<usize>
+    {
+	Some(VirtualTerminal::width(self))
+    }
+    #[inline]
+    fn is_terminal(&self) -> bool
+    {
+	true
+    }
+    #[inline]
+    fn draw_mode(&self) -> lines::DrawMode
+    {
+	lines::DrawMode::Terminal
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn interpreting()
+    {
+	let mut term = VirtualTerminal::new(10, 3);
+	write!(term, "hello\rj").unwrap();
+	assert_eq!(term.screen(), ["jello", "", ""]);
+	write!(term, "\x1B[K\nab\x08c\x1B[1Ax").unwrap();
+	assert_eq!(term.screen(), ["j x", "ac", ""]);
+	assert_eq!(term.cursor(), (0, 3));
+	// Split across writes.
+	term.write_all(b"\x1B[3;2").unwrap();
+	term.write_all(b"H\xE6\x97").unwrap();
+	term.write_all(b"\xA5e\xCC\x81\x1B[?25l").unwrap();
+	assert_eq!(term.screen(), ["j x", "ac", " 日e\u{301}"]);
+	assert!(!term.cursor_visible());
+	write!(term, "\n0123456789ab").unwrap();
+	assert_eq!(term.scrollback(), ["j x", "ac"]);
+	assert_eq!(term.contents(), "j x\nac\n 日e\u{301}\n0123456789\nab");
+    }
+
+    #[test]
+    fn drawing_under_ci()
+    {
+	let previous = std::env::var_os("CI");
+	// SAFETY: The environment is only read through `std::env`, which synchronises with this.
+	unsafe { std::env::set_var("CI", "true") };
+	let term = VirtualTerminal::new(40, 3);
+	let mut bar: progress::Bar<VirtualTerminal> = progress::Bar::with_size(term.clone(), 10, term.clone());
+	bar.set_progress(0.5);
+	let mode = bar.draw_mode();
+	match previous {
+	    // SAFETY: As above.
+	    Some(value) => unsafe { std::env::set_var("CI", value) },
+	    None => unsafe { std::env::remove_var("CI") },
+	}
+	assert_eq!(mode, lines::DrawMode::Terminal);
+	assert_eq!(term.screen(), ["[=====     ]: 50.00%", "", ""]);
+    }
+}
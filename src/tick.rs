@@ -0,0 +1,58 @@
+//! Redrawing indicators at a fixed interval on a background thread.
+//!
+//! See `handle::BarHandle::steady_tick()` and `spinner::Spin::steady_tick()`.
+
+use std::{
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+/// Ticks an indicator at a fixed interval on a background thread, until it is dropped.
+///
+/// Dropping this stops the thread (after it draws anything left undrawn) and waits for it to exit.
+#[derive(Debug)]
+pub struct SteadyTick
+{
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl SteadyTick
+{
+    /// Call `tick` every `interval` on a new thread, and `stopped` once when this is dropped.
+    pub(crate) fn spawn<F, S>(interval: Duration, mut tick: F, stopped: S) -> Self
+    where F: FnMut() + Send + 'static,
+	  S: FnOnce() + Send + 'static
+    {
+	let (stop, stopping) = mpsc::channel::<()>();
+	let thread = thread::spawn(move || {
+	    while let Err(mpsc::RecvTimeoutError::Timeout) = stopping.recv_timeout(interval) {
+		tick();
+	    }
+	    stopped();
+	});
+	Self {
+	    stop: Some(stop),
+	    thread: Some(thread),
+	}
+    }
+
+    /// Stop ticking and wait for the thread to exit.
+    ///
+    /// This is the same as dropping it.
+    #[inline]
+    pub fn stop(self) {}
+}
+
+impl Drop for SteadyTick
+{
+    fn drop(&mut self)
+    {
+	// Disconnecting the channel wakes the thread.
+	drop(self.stop.take());
+	if let Some(thread) = self.thread.take() {
+	    let _ = thread.join();
+	}
+    }
+}
@@ -1,47 +1,220 @@
-//! Contains infinite `char` iterators and the type wrapping them, `Wheel`.
+//! Contains the repeating series of spinner frames, `Wheel`, and the infinite iterators over them.
 
-/// An infinite repeating series of `char`. Used for `spinner::Spin`.
+use super::width;
+use std::{
+    error,
+    fmt,
+    str::FromStr,
+};
+
+/// An infinite repeating series of frames. Used for `spinner::Spin`, and the `{spinner}` of a `progress::Bar`.
+///
+/// A frame is a string, so it can be more than one character (e.g. `[=   ]`) or more than one codepoint (e.g. an emoji sequence.)
+/// Frames narrower than the widest frame of the wheel are padded with spaces when drawn, so every frame takes up the same number of columns (see `width()`.)
+///
+/// `iter()` yields an infinite iterator of the repeating series of frames. It also implements the `IntoIterator` trait, yielding the frames as owned `String`s.
 ///
-/// It implements the `IntoIterator` trait, yielding an infinite iterator of a repeating series of `char`s.
+/// # Presets
+/// Well-known spinners are available as constants (e.g. `Wheel::DOTS`), and can be looked up by name with `from_name()` or `parse()`, e.g. from a config file:
+/// ```rust
+/// # use termprogress::wheel::Wheel;
+/// let wheel: Wheel = "dots".parse().unwrap();
+/// assert_eq!(wheel.frame(0), "⠋");
+/// assert!(Wheel::PRESETS.iter().any(|&(name, _)| name == "moon"));
+/// ```
 #[derive(Clone,Debug)]
 pub enum Wheel
 {
-    Static(&'static [char]),
-    Dynamic(Box<[char]>),
+    Static(&'static [&'static str]),
+    Dynamic(Box<[String]>),
 }
 
 impl Wheel
 {
-    /// Create a new spinner out of an interator of `char`.
+    /// `/-\|`, the default.
+    pub const DEFAULT: Self = Self::Static(&["/", "-", "\\", "|"]);
+    /// `-\|/`
+    pub const LINE: Self = Self::Static(&["-", "\\", "|", "/"]);
+    /// Braille dots circling a single cell.
+    pub const DOTS: Self = Self::Static(&["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"]);
+    /// A gap circling a full braille cell.
+    pub const DOTS2: Self = Self::Static(&["⣾", "⣽", "⣻", "⢿", "⡿", "⣟", "⣯", "⣷"]);
+    /// A braille dot bouncing up and down.
+    pub const BOUNCE: Self = Self::Static(&["⠁", "⠂", "⠄", "⠂"]);
+    /// An arc turning around a circle.
+    pub const ARC: Self = Self::Static(&["◜", "◠", "◝", "◞", "◡", "◟"]);
+    /// A half-filled circle turning.
+    pub const CIRCLE: Self = Self::Static(&["◐", "◓", "◑", "◒"]);
+    /// Box-drawing corners turning around a cell.
+    pub const PIPE: Self = Self::Static(&["┤", "┘", "┴", "└", "├", "┌", "┬", "┐"]);
+    /// An arrow turning clockwise.
+    pub const ARROWS: Self = Self::Static(&["←", "↖", "↑", "↗", "→", "↘", "↓", "↙"]);
+    /// A ball bouncing between brackets, in ASCII.
+    pub const BOUNCING_BAR: Self = Self::Static(&["[=   ]", "[ =  ]", "[  = ]", "[   =]", "[  = ]", "[ =  ]"]);
+    /// Dots filling up, in ASCII.
+    pub const ELLIPSIS: Self = Self::Static(&["   ", ".  ", ".. ", "..."]);
+    /// A clock face going round the hours.
+    pub const CLOCK: Self = Self::Static(&["🕛", "🕐", "🕑", "🕒", "🕓", "🕔", "🕕", "🕖", "🕗", "🕘", "🕙", "🕚"]);
+    /// The phases of the moon.
+    pub const MOON: Self = Self::Static(&["🌑", "🌒", "🌓", "🌔", "🌕", "🌖", "🌗", "🌘"]);
+
+    /// The preset wheels, by the name `from_name()` looks them up with.
+    pub const PRESETS: [(&'static str, Self); 13] = [
+	("default", Self::DEFAULT),
+	("line", Self::LINE),
+	("dots", Self::DOTS),
+	("dots2", Self::DOTS2),
+	("bounce", Self::BOUNCE),
+	("arc", Self::ARC),
+	("circle", Self::CIRCLE),
+	("pipe", Self::PIPE),
+	("arrows", Self::ARROWS),
+	("bouncing_bar", Self::BOUNCING_BAR),
+	("ellipsis", Self::ELLIPSIS),
+	("clock", Self::CLOCK),
+	("moon", Self::MOON),
+    ];
+
+    /// Look up a preset wheel by its name in `PRESETS`, ignoring case.
+    ///
+    /// `-` and `_` are interchangeable, so `bouncing-bar` is `BOUNCING_BAR`.
+    pub fn from_name(name: &str) -> Option<Self>
+    {
+	let matches = |preset: &str| preset.len() == name.len() && preset.bytes().zip(name.bytes()).all(|(p, n)| {
+	    p.eq_ignore_ascii_case(&n) || (p == b'_' && n == b'-')
+	});
+	Self::PRESETS.into_iter().find(|(preset, _)| matches(preset)).map(|(_, wheel)| wheel)
+    }
+
+    /// Create a new spinner out of an interator of frames, e.g. `char`s or strings.
     pub fn new<T>(iter: T) -> Self
-    where T: IntoIterator<Item=char>
+    where T: IntoIterator,
+	  T::Item: Into<String>
     {
-	let col: Vec<char> = iter.into_iter().collect();
+	let col: Vec<String> = iter.into_iter().map(Into::into).collect();
 	Self::Dynamic(col.into_boxed_slice())
     }
 
-    /// Get a reference to all the possible chars of this wheel.
-    pub fn chars(&self) -> &[char]
+    /// The number of frames in this wheel.
+    #[inline]
+    pub fn len(&self) -> usize
+    {
+	match self
+	{
+	    Wheel::Static(s) => s.len(),
+	    Wheel::Dynamic(b) => b.len(),
+	}
+    }
+
+    /// Does this wheel have no frames?
+    #[inline]
+    pub fn is_empty(&self) -> bool
     {
-	match &self
+	self.len() == 0
+    }
+
+    /// Get frame `idx`, wrapping around to the start of the wheel after the last frame.
+    ///
+    /// An empty wheel only has the empty frame.
+    pub fn frame(&self, idx: usize) -> &str
+    {
+	if self.is_empty() {
+	    return "";
+	}
+	let idx = idx % self.len();
+	match self
 	{
-	    Wheel::Static(s) => s,
-	    Wheel::Dynamic(b) => &b[..],
+	    Wheel::Static(s) => s[idx],
+	    Wheel::Dynamic(b) => &b[idx],
+	}
+    }
+
+    /// An infinite iterator of the repeating series of frames.
+    #[inline]
+    pub fn iter(&self) -> Frames<'_>
+    {
+	Frames {
+	    wheel: self,
+	    idx: 0,
 	}
     }
+
+    /// Get the first `char` of every frame of this wheel.
+    #[deprecated = "frames are strings, use `frame()` or `iter()`"]
+    pub fn chars(&self) -> Vec<char>
+    {
+	(0..self.len()).filter_map(|i| self.frame(i).chars().next()).collect()
+    }
+
+    /// The number of columns each frame is drawn in, which is the width of the widest frame.
+    pub fn width(&self) -> usize
+    {
+	(0..self.len()).map(|i| width::width(self.frame(i))).max().unwrap_or(0)
+    }
+
+    /// Write frame `idx` into `out`, padded to `width()`.
+    pub(crate) fn render_into(&self, out: &mut String, idx: usize, width: usize)
+    {
+	let frame = self.frame(idx);
+	out.push_str(frame);
+	out.extend(std::iter::repeat_n(' ', width.saturating_sub(width::width(frame))));
+    }
 }
 
 impl Default for Wheel
 {
+    #[inline]
     fn default() -> Self
     {
-	DEFAULT_WHEEL.clone()
+	Self::DEFAULT
     }
 }
 
-const DEFAULT_WHEEL: Wheel = Wheel::Static(&['/', '-', '\\', '|']);
+/// The error returned when parsing the name of a wheel that is not one of `Wheel::PRESETS`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownWheel(pub String);
+
+impl error::Error for UnknownWheel{}
 
-/// An infinite iterator of a repeating series of `char`s, used for `spinner::Spin`
+impl fmt::Display for UnknownWheel
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+	write!(f, "unknown spinner {:?}", self.0)
+    }
+}
+
+impl FromStr for Wheel
+{
+    type Err = UnknownWheel;
+    /// Look up a preset wheel by name, see `from_name()`.
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+	Self::from_name(s).ok_or_else(|| UnknownWheel(s.to_owned()))
+    }
+}
+
+/// An infinite iterator of a repeating series of frames, see `Wheel::iter()`.
+#[derive(Debug, Clone)]
+pub struct Frames<'a>
+{
+    wheel: &'a Wheel,
+    idx: usize,
+}
+
+impl<'a> Iterator for Frames<'a>
+{
+    type Item = &'a str;
+    fn next(&mut self) -> Option<&'a str>
+    {
+	let frame = self.wheel.frame(self.idx);
+	self.idx = self.idx.wrapping_add(1);
+	Some(frame)
+    }
+}
+
+/// An infinite iterator of a repeating series of owned frames, see `IntoIterator for Wheel`.
 #[derive(Debug, Clone)]
 pub struct WheelIntoIter
 {
@@ -51,24 +224,78 @@ pub struct WheelIntoIter
 
 impl Iterator for WheelIntoIter
 {
-    type Item = char;
-    fn next(&mut self) -> Option<char>
+    type Item = String;
+    fn next(&mut self) -> Option<String>
     {
-	let chars = self.source.chars();
-	self.idx = (self.idx + 1) % chars.len();
-	Some(chars[self.idx])
+	let frame = self.source.frame(self.idx).to_owned();
+	self.idx = self.idx.wrapping_add(1);
+	Some(frame)
     }
 }
 
 impl IntoIterator for Wheel
 {
-    type IntoIter= WheelIntoIter;
-    type Item = char;
+    type IntoIter = WheelIntoIter;
+    type Item = String;
+    #[inline]
     fn into_iter(self) -> Self::IntoIter
     {
-	WheelIntoIter{
+	WheelIntoIter {
 	    source: self,
 	    idx: 0,
 	}
     }
 }
+
+impl<'a> IntoIterator for &'a Wheel
+{
+    type IntoIter = Frames<'a>;
+    type Item = &'a str;
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter
+    {
+	self.iter()
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    #[test]
+    fn frames()
+    {
+	let wheel = Wheel::new(['a', 'b']);
+	assert_eq!(wheel.iter().take(5).collect::<Vec<_>>(), ["a", "b", "a", "b", "a"]);
+	assert_eq!(wheel.width(), 1);
+
+	let wheel = Wheel::new(["[=  ]", "[ = ]", "[  =]"]);
+	assert_eq!(wheel.frame(4), "[ = ]");
+	assert_eq!(wheel.width(), 5);
+
+	let wheel = Wheel::Static(&["🕐", "🕑", "👨\u{200d}👩\u{200d}👧", "."]);
+	assert_eq!(wheel.width(), 2);
+	let mut out = String::new();
+	wheel.render_into(&mut out, 3, wheel.width());
+	assert_eq!(out, ". ");
+
+	assert_eq!(Wheel::new(Vec::<char>::new()).frame(3), "");
+	assert_eq!(Wheel::LINE.into_iter().take(5).collect::<Vec<_>>(), ["-", "\\", "|", "/", "-"]);
+	#[allow(deprecated)]
+	let chars = Wheel::new(["ab", "c"]).chars();
+	assert_eq!(chars, ['a', 'c']);
+    }
+
+    #[test]
+    fn presets()
+    {
+	for (name, wheel) in Wheel::PRESETS {
+	    assert!(!wheel.is_empty(), "{name}");
+	    assert_eq!(Wheel::from_name(name).map(|w| w.frame(1).to_owned()), Some(wheel.frame(1).to_owned()));
+	    assert!(wheel.iter().take(wheel.len()).all(|f| width::width(f) == wheel.width()), "{name} has frames of different widths");
+	}
+	assert_eq!("Bouncing-Bar".parse::<Wheel>().unwrap().width(), 6);
+	assert_eq!("spiral".parse::<Wheel>().unwrap_err(), UnknownWheel("spiral".to_owned()));
+    }
+}
@@ -0,0 +1,196 @@
+//! Measuring, truncating and padding text by the number of terminal columns it takes up.
+//!
+//! Text is measured a grapheme cluster at a time with the East Asian Width tables, so wide (e.g. CJK) characters take up 2 columns, combining marks take up none, and an emoji sequence joined with zero-width joiners takes up the width of one emoji.
+//! Escape sequences (e.g. colours) take up no columns, and are never cut by truncation.
+
+use unicode_segmentation::{
+    Graphemes,
+    UnicodeSegmentation,
+};
+use unicode_width::UnicodeWidthStr;
+
+/// A piece of text that is measured as a whole.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token<'a>
+{
+    /// A `CSI` escape sequence.
+    Escape(&'a str),
+    /// A grapheme cluster.
+    Grapheme(&'a str),
+}
+
+impl Token<'_>
+{
+    /// The number of columns this token takes up.
+    #[inline]
+    fn width(&self) -> usize
+    {
+	match self {
+	    Self::Escape(_) => 0,
+	    Self::Grapheme(g) => g.width(),
+	}
+    }
+}
+
+/// The length in bytes of the escape sequence at the start of `s`, if there is one.
+pub(crate) fn escape_len(s: &str) -> Option<usize>
+{
+    let rest = s.strip_prefix("\x1B[")?;
+    // Parameter and intermediate bytes, then a final byte.
+    let end = rest.bytes().position(|b| (0x40..=0x7e).contains(&b))?;
+    Some(2 + end + 1)
+}
+
+/// Split `s` into escape sequences and grapheme clusters.
+fn tokens(s: &str) -> impl Iterator<Item = Token<'_>>
+{
+    let mut rest = s;
+    let mut run: Graphemes<'_> = "".graphemes(true);
+    std::iter::from_fn(move || loop {
+	if let Some(grapheme) = run.next() {
+	    return Some(Token::Grapheme(grapheme));
+	}
+	if rest.is_empty() {
+	    return None;
+	}
+	if let Some(len) = escape_len(rest) {
+	    let (escape, after) = rest.split_at(len);
+	    rest = after;
+	    return Some(Token::Escape(escape));
+	}
+	// The text up to the next escape sequence.
+	let end = rest.match_indices("\x1B[").map(|(i, _)| i).find(|&i| i > 0).unwrap_or(rest.len());
+	let (text, after) = rest.split_at(end);
+	run = text.graphemes(true);
+	rest = after;
+    })
+}
+
+/// The number of columns `s` takes up in a terminal.
+pub fn width(s: &str) -> usize
+{
+    // Printable ASCII takes up a column a byte, and has no escapes.
+    if s.bytes().all(|b| matches!(b, 0x20..0x7f)) {
+	return s.len();
+    }
+    tokens(s).map(|t| t.width()).sum()
+}
+
+/// Write as much of the start of `s` into `out` as fits in `width` columns, keeping all its escape sequences.
+///
+/// Returns the number of columns written.
+fn take_into(out: &mut String, s: &str, width: usize) -> usize
+{
+    let mut used = 0;
+    let mut full = false;
+    for token in tokens(s) {
+	match token {
+	    Token::Escape(escape) => out.push_str(escape),
+	    // Once a grapheme does not fit, narrower ones after it must not be written in its place.
+	    Token::Grapheme(_) if full => (),
+	    Token::Grapheme(grapheme) => {
+		let w = token.width();
+		if used + w <= width {
+		    out.push_str(grapheme);
+		    used += w;
+		} else {
+		    full = true;
+		}
+	    },
+	}
+    }
+    used
+}
+
+/// Write `s` into `out`, truncated with `...` if it takes up more than `width` columns.
+///
+/// Returns the number of columns written, which may be less than `width` if a wide character did not fit.
+pub(crate) fn truncate_into(out: &mut String, s: &str, width: usize) -> usize
+{
+    let len = self::width(s);
+    if len <= width {
+	out.push_str(s);
+	len
+    } else if width > 3 {
+	let used = take_into(out, s, width - 3);
+	out.push_str("...");
+	used + 3
+    } else {
+	take_into(out, s, width)
+    }
+}
+
+/// Write `s` into `out`, padded or truncated so it takes up exactly `width` columns.
+///
+/// Escape sequences are all kept, so styles are still reset after truncation.
+pub(crate) fn fit_into(out: &mut String, s: &str, width: usize)
+{
+    let len = self::width(s);
+    let used = if len <= width {
+	out.push_str(s);
+	len
+    } else {
+	// A wide character may not fit in the last column.
+	take_into(out, s, width)
+    };
+    out.extend(std::iter::repeat_n(' ', width - used));
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    fn truncate(s: &str, width: usize) -> String
+    {
+	let mut out = String::new();
+	truncate_into(&mut out, s, width);
+	out
+    }
+
+    fn fit(s: &str, width: usize) -> String
+    {
+	let mut out = String::new();
+	fit_into(&mut out, s, width);
+	out
+    }
+
+    #[test]
+    fn widths()
+    {
+	assert_eq!(width("title"), 5);
+	assert_eq!(width("日本語のタイトル"), 16);
+	assert_eq!(width("e\u{301}te\u{301}"), 3);
+	assert_eq!(width("👨\u{200d}👩\u{200d}👧 family"), 9);
+	assert_eq!(width("\x1B[1;32mok\x1B[0m"), 2);
+    }
+
+    #[test]
+    fn truncating_mixed_widths()
+    {
+	assert_eq!(truncate("日本語のタイトル", 16), "日本語のタイトル");
+	assert_eq!(truncate("日本語のタイトル", 9), "日本語...");
+	assert_eq!(truncate("日本語のタイトル", 10), "日本語...");
+	assert_eq!(truncate("日本語のタイトル", 11), "日本語の...");
+	assert_eq!(truncate("a👨\u{200d}👩\u{200d}👧bcdef", 6), "a👨\u{200d}👩\u{200d}👧...");
+	assert_eq!(truncate("e\u{301}e\u{301}e\u{301}e\u{301}e\u{301}", 4), "e\u{301}...");
+	assert_eq!(truncate("\x1B[1mbold title\x1B[0m", 7), "\x1B[1mbold\x1B[0m...");
+	assert_eq!(truncate("日本語abcdefgh", 8), "日本...");
+	assert_eq!(truncate("ab日本語cdefgh", 8), "ab日...");
+	assert_eq!(truncate("\x1B[31m日本語\x1B[0mabcdefgh", 8), "\x1B[31m日本\x1B[0m...");
+    }
+
+    #[test]
+    fn fitting()
+    {
+	assert_eq!(fit("日本語", 8), "日本語  ");
+	assert_eq!(fit("日本語", 5), "日本 ");
+	assert_eq!(fit("\x1B[32m日本語\x1B[0m", 3), "\x1B[32m日\x1B[0m ");
+	assert_eq!(fit("日本語abc", 5), "日本 ");
+	for title in ["日本語のタイトル", "emoji 🎉🎉🎉", "a\u{301}b\u{301}c\u{301}"] {
+	    for w in 0..20 {
+		assert_eq!(width(&fit(title, w)), w);
+	    }
+	}
+    }
+}